@@ -0,0 +1,133 @@
+use crate::game::Termination;
+use crate::uci::Score;
+use board_game_traits::Color;
+use pgn_traits::PgnPosition;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdjudicationSettings {
+    /// Draw the game after this many plies, including the opening
+    pub max_plies: Option<usize>,
+    /// Draw the game when the same position has occurred this many times
+    pub repetitions: Option<usize>,
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+}
+
+impl Default for AdjudicationSettings {
+    fn default() -> Self {
+        AdjudicationSettings {
+            max_plies: Some(1000),
+            repetitions: Some(3),
+            resign: None,
+            draw: None,
+        }
+    }
+}
+
+/// Adjudicate a win if both engines' scores have been above `score` for `moves` moves each
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResignRule {
+    pub moves: usize,
+    pub score: i64,
+}
+
+/// Adjudicate a draw if both engines' scores have been below `score` for `moves` moves each, after ply `start_ply`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawRule {
+    pub start_ply: usize,
+    pub moves: usize,
+    pub score: i64,
+}
+
+/// Keeps track of a single game, and decides whether it should be adjudicated
+pub struct Adjudicator<'a> {
+    settings: &'a AdjudicationSettings,
+    position_counts: HashMap<String, usize>,
+    last_position_count: usize,
+    // Each ply's score, from white's perspective, if the engine reported one
+    white_scores: Vec<Option<Score>>,
+}
+
+impl<'a> Adjudicator<'a> {
+    pub fn new<B: PgnPosition>(settings: &'a AdjudicationSettings, root_position: &B) -> Self {
+        let mut adjudicator = Adjudicator {
+            settings,
+            position_counts: HashMap::new(),
+            last_position_count: 0,
+            white_scores: vec![],
+        };
+        adjudicator.count_position(root_position);
+        adjudicator
+    }
+
+    /// Register the position after a move, and the score that the moving engine gave it, from white's perspective
+    pub fn add_position<B: PgnPosition>(&mut self, position: &B, white_score: Option<Score>) {
+        self.count_position(position);
+        self.white_scores.push(white_score);
+    }
+
+    fn count_position<B: PgnPosition>(&mut self, position: &B) {
+        // The last field of a TPS string is the move number, which does not matter for repetitions
+        let tps = position.to_fen();
+        let key = match tps.rsplit_once(' ') {
+            Some((key, _)) => key.to_string(),
+            None => tps,
+        };
+        let count = self.position_counts.entry(key).or_insert(0);
+        *count += 1;
+        self.last_position_count = *count;
+    }
+
+    /// Returns the game result and reason, if the current position should be adjudicated
+    pub fn adjudicate(&self) -> Option<(&'static str, Termination)> {
+        let num_plies = self.white_scores.len();
+
+        if let Some(max_plies) = self.settings.max_plies {
+            if num_plies >= max_plies {
+                return Some(("1/2-1/2", Termination::MoveLimit(num_plies)));
+            }
+        }
+
+        if let Some(repetitions) = self.settings.repetitions {
+            if self.last_position_count >= repetitions {
+                return Some(("1/2-1/2", Termination::Repetition(repetitions)));
+            }
+        }
+
+        if let Some(rule) = self.settings.resign {
+            if let Some(scores) = self.last_scores(2 * rule.moves) {
+                if scores.iter().all(|score| *score > rule.score) {
+                    return Some(("1-0", Termination::Resignation(Color::Black)));
+                }
+                if scores.iter().all(|score| *score < -rule.score) {
+                    return Some(("0-1", Termination::Resignation(Color::White)));
+                }
+            }
+        }
+
+        if let Some(rule) = self.settings.draw {
+            if num_plies >= rule.start_ply + 2 * rule.moves {
+                if let Some(scores) = self.last_scores(2 * rule.moves) {
+                    if scores.iter().all(|score| score.abs() < rule.score) {
+                        return Some(("1/2-1/2", Termination::ScoreDraw));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The scores in centiflats from the last `n` plies, if every one of them had a score.
+    /// Mate scores are always above the resign threshold
+    fn last_scores(&self, n: usize) -> Option<Vec<i64>> {
+        if n == 0 || self.white_scores.len() < n {
+            return None;
+        }
+        self.white_scores[self.white_scores.len() - n..]
+            .iter()
+            .map(|score| score.map(Score::to_cp))
+            .collect()
+    }
+}
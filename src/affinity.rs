@@ -0,0 +1,131 @@
+use std::io;
+use std::str::FromStr;
+
+/// Number of cpus in a `cpu_set_t`
+#[cfg(target_os = "linux")]
+const CPU_SET_SIZE: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const CPU_SET_SIZE: usize = 1024;
+
+/// How the cores are shared out between the workers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AffinityPolicy {
+    /// Each worker gets one core, which both engines in its games run on
+    #[default]
+    PerGame,
+    /// Each engine in each worker gets its own core
+    PerEngine,
+}
+
+impl FromStr for AffinityPolicy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "game" => Ok(AffinityPolicy::PerGame),
+            "engine" => Ok(AffinityPolicy::PerEngine),
+            _ => Err(format!(
+                "Invalid affinity policy \"{}\", expected game or engine",
+                input
+            )),
+        }
+    }
+}
+
+/// Pin each worker thread, and the engine processes it starts, to cores from a list
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AffinitySettings {
+    pub cores: Vec<usize>,
+    pub policy: AffinityPolicy,
+}
+
+impl AffinitySettings {
+    pub fn cores_needed(&self, concurrency: usize, num_engines: usize) -> usize {
+        match self.policy {
+            AffinityPolicy::PerGame => concurrency,
+            AffinityPolicy::PerEngine => concurrency * num_engines,
+        }
+    }
+
+    /// The core that the engine runs on, in the given worker
+    pub fn engine_core(&self, worker_id: usize, engine_id: usize, num_engines: usize) -> usize {
+        match self.policy {
+            AffinityPolicy::PerGame => self.cores[worker_id],
+            AffinityPolicy::PerEngine => self.cores[worker_id * num_engines + engine_id],
+        }
+    }
+
+    /// All cores used by the worker's engines
+    pub fn worker_cores(&self, worker_id: usize, num_engines: usize) -> Vec<usize> {
+        match self.policy {
+            AffinityPolicy::PerGame => vec![self.cores[worker_id]],
+            AffinityPolicy::PerEngine => {
+                self.cores[worker_id * num_engines..(worker_id + 1) * num_engines].to_vec()
+            }
+        }
+    }
+}
+
+/// Parse a list of cores like `0-7,16,18`. Each core may only be listed once
+pub fn parse_core_list(input: &str) -> Result<Vec<usize>, String> {
+    let mut cores = vec![];
+    for part in input.split(',') {
+        let parse_core = |s: &str| -> Result<usize, String> {
+            let core = s
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid core \"{}\" in core list \"{}\"", s, input))?;
+            if core >= CPU_SET_SIZE {
+                return Err(format!(
+                    "Core {} is too high, the highest supported core is {}",
+                    core,
+                    CPU_SET_SIZE - 1
+                ));
+            }
+            Ok(core)
+        };
+        let range = match part.split_once('-') {
+            Some((first, last)) => parse_core(first)?..=parse_core(last)?,
+            None => {
+                let core = parse_core(part)?;
+                core..=core
+            }
+        };
+        if range.is_empty() {
+            return Err(format!("Empty core range \"{}\"", part));
+        }
+        for core in range {
+            if cores.contains(&core) {
+                return Err(format!("Core {} is listed more than once", core));
+            }
+            cores.push(core);
+        }
+    }
+    Ok(cores)
+}
+
+/// Restrict the calling thread to the cores. Processes started from the thread afterwards inherit the restriction
+#[cfg(target_os = "linux")]
+pub fn set_thread_affinity(cores: &[usize]) -> io::Result<()> {
+    // Safety: An all-zero cpu set is valid, and the cores are below `CPU_SET_SIZE`
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &core in cores {
+        unsafe { libc::CPU_SET(core, &mut cpu_set) };
+    }
+    // A pid of 0 means the calling thread
+    // Safety: The cpu set is valid for reads of the given size
+    let result = unsafe { libc::sched_setaffinity(0, std::mem::size_of_val(&cpu_set), &cpu_set) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_affinity(_cores: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is only supported on Linux",
+    ))
+}
@@ -0,0 +1,519 @@
+//! Protocol conformance tests for a single engine, run with the `check-engine` subcommand.
+//! Each check is independent, and the engine is restarted after a failed check.
+
+use crate::cli::CheckEngineOptions;
+use crate::engine::{Engine, EngineBuilder, SearchLimits};
+use crate::uci::parser::{parse_info_string, parse_option};
+use crate::uci::{UciOption, UciOptionType};
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tiltak::position::{Komi, Position};
+
+/// Clock for the regular searches. Engines typically use a small fraction of it
+const SEARCH_TIME: Duration = Duration::from_secs(10);
+const SEARCH_INCREMENT: Duration = Duration::from_millis(100);
+/// Clock for the time pressure test
+const LOW_TIME: Duration = Duration::from_millis(100);
+/// Number of plies played from the start position, for the tps tests
+const POSITION_PLIES: usize = 6;
+/// Komi for the komi test, in half flats
+const HALF_KOMI: i8 = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed(String),
+    Failed(String),
+    /// The engine does not advertise the feature being tested
+    Skipped(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: String,
+    pub outcome: CheckOutcome,
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (status, details) = match &self.outcome {
+            CheckOutcome::Passed(details) => ("PASS", details),
+            CheckOutcome::Failed(details) => ("FAIL", details),
+            CheckOutcome::Skipped(details) => ("SKIP", details),
+        };
+        if details.is_empty() {
+            write!(f, "[{}] {}", status, self.name)
+        } else {
+            write!(f, "[{}] {}: {}", status, self.name, details)
+        }
+    }
+}
+
+/// Run all checks against the engine, and print a report. Returns whether every check passed
+pub fn run(options: &CheckEngineOptions) -> bool {
+    let builder = EngineBuilder {
+        path: options.engine.path.clone(),
+        name: options.engine.name.clone(),
+        args: options.engine.cli_args.clone(),
+        working_dir: options.engine.working_dir.clone(),
+        env_vars: options.engine.env_vars.clone(),
+        desired_uci_options: options.engine.tei_settings.clone(),
+        limits: SearchLimits::default(),
+        timeout_margin: options.timeout,
+        untimed_timeout: options.timeout,
+        stderr: options.engine.stderr.clone(),
+        stderr_file_name: None,
+        worker_id: None,
+        cpu_core: None,
+        resource_limits: options.engine.resource_limits,
+    };
+    println!(
+        "Checking {}",
+        builder.name.as_ref().unwrap_or(&builder.path)
+    );
+    println!();
+
+    let mut checker = Checker {
+        builder,
+        timeout: options.timeout,
+        engine: None,
+        options: vec![],
+        results: vec![],
+    };
+    checker.run_all();
+
+    for result in checker.results.iter() {
+        println!("{}", result);
+    }
+    let failed = checker
+        .results
+        .iter()
+        .filter(|result| matches!(result.outcome, CheckOutcome::Failed(_)))
+        .count();
+    let skipped = checker
+        .results
+        .iter()
+        .filter(|result| matches!(result.outcome, CheckOutcome::Skipped(_)))
+        .count();
+    println!();
+    println!(
+        "{} passed, {} failed, {} skipped",
+        checker.results.len() - failed - skipped,
+        failed,
+        skipped
+    );
+    failed == 0
+}
+
+struct Checker {
+    builder: EngineBuilder,
+    timeout: Duration,
+    /// `None` if the engine could not be started
+    engine: Option<Engine>,
+    /// Options advertised by the engine during the handshake
+    options: Vec<UciOption>,
+    results: Vec<CheckResult>,
+}
+
+impl Checker {
+    fn run_all(&mut self) {
+        match self.start_engine() {
+            Ok(malformed_options) => {
+                self.record("Handshake", Ok(format!("{} options", self.options.len())));
+                for (line, err) in malformed_options {
+                    self.record(&format!("Option line \"{}\"", line), Err(err));
+                }
+            }
+            Err(err) => {
+                self.record("Handshake", Err(err));
+                return;
+            }
+        }
+
+        let timeout = self.timeout;
+        self.check("isready", |engine| {
+            is_ready(engine, timeout)?;
+            Ok(String::new())
+        });
+
+        for option in self.options.clone() {
+            self.check(&format!("Option {}", option.name), |engine| {
+                check_option(engine, &option, timeout)
+            });
+        }
+
+        for (name, value) in self.builder.desired_uci_options.clone() {
+            let option = self.options.iter().find(|option| option.name == name);
+            let outcome = match option {
+                Some(option) if option.option_type.value_is_supported(&value) => None,
+                Some(_) => Some(CheckOutcome::Failed(format!(
+                    "Value {} is not supported by the engine",
+                    value
+                ))),
+                None => Some(CheckOutcome::Failed(
+                    "Option is not advertised by the engine".to_string(),
+                )),
+            };
+            let check_name = format!("Set option {}={}", name, value);
+            if let Some(outcome) = outcome {
+                self.results.push(CheckResult {
+                    name: check_name,
+                    outcome,
+                });
+                continue;
+            }
+            self.check(&check_name, |engine| {
+                write_line(engine, &format!("setoption name {} value {}", name, value))?;
+                is_ready(engine, timeout)?;
+                Ok(String::new())
+            });
+        }
+
+        self.check_size::<4>();
+        self.check_size::<5>();
+        self.check_size::<6>();
+        self.check_size::<7>();
+        self.check_size::<8>();
+
+        self.check_komi();
+        self.check_time_pressure();
+        self.check_quit();
+    }
+
+    /// Start the engine and read its options, returning the option lines that could not be parsed.
+    /// Options from the command line are set immediately after the handshake
+    fn start_engine(&mut self) -> Result<Vec<(String, String)>, String> {
+        let mut engine = self
+            .builder
+            .spawn()
+            .map_err(|err| format!("Failed to start engine: {}", err))?;
+        write_line(&mut engine, "tei")?;
+        let deadline = Instant::now() + self.timeout;
+        let mut options = vec![];
+        let mut malformed_options = vec![];
+        loop {
+            let input = read_line(&mut engine, deadline, "teiok")?;
+            match input.split_whitespace().next() {
+                Some("teiok") => break,
+                Some("option") => match parse_option(&input) {
+                    Ok(option) => options.push(option),
+                    Err(err) => malformed_options.push((input.trim().to_string(), err.to_string())),
+                },
+                _ => (),
+            }
+        }
+        for (name, value) in self.builder.desired_uci_options.iter() {
+            if options.iter().any(|option: &UciOption| {
+                option.name == *name && option.option_type.value_is_supported(value)
+            }) {
+                write_line(
+                    &mut engine,
+                    &format!("setoption name {} value {}", name, value),
+                )?;
+            }
+        }
+        self.engine = Some(engine);
+        self.options = options;
+        Ok(malformed_options)
+    }
+
+    /// Run a single check. If it fails, the engine is restarted for the next check
+    fn check<F>(&mut self, name: &str, check: F)
+    where
+        F: FnOnce(&mut Engine) -> Result<String, String>,
+    {
+        let Some(engine) = self.engine.as_mut() else {
+            self.record(name, Err("Engine is not running".to_string()));
+            return;
+        };
+        let outcome = check(engine);
+        let failed = outcome.is_err();
+        if failed {
+            let _ = engine.shutdown();
+            self.engine = None;
+        }
+        self.record(name, outcome);
+        if failed {
+            if let Err(err) = self.start_engine() {
+                self.record("Restart after failed check", Err(err));
+            }
+        }
+    }
+
+    fn record(&mut self, name: &str, outcome: Result<String, String>) {
+        self.results.push(CheckResult {
+            name: name.to_string(),
+            outcome: match outcome {
+                Ok(details) => CheckOutcome::Passed(details),
+                Err(details) => CheckOutcome::Failed(details),
+            },
+        })
+    }
+
+    /// Start a new game of the given size, and search from the start position and from a tps position
+    fn check_size<const S: usize>(&mut self) {
+        let timeout = self.timeout;
+        self.check(&format!("teinewgame {}", S), |engine| {
+            write_line(engine, &format!("teinewgame {}", S))?;
+            is_ready(engine, timeout)?;
+            Ok(String::new())
+        });
+
+        let start_position = Position::<S>::start_position();
+        self.check(&format!("{}s position startpos", S), |engine| {
+            new_game(engine, S, timeout)?;
+            search(
+                engine,
+                &start_position,
+                "position startpos",
+                &clock_go_string(SEARCH_TIME, SEARCH_INCREMENT),
+                SEARCH_TIME + timeout,
+            )
+        });
+
+        let mut position = start_position.clone();
+        let mut moves = vec![];
+        for ply in 0..POSITION_PLIES {
+            let mut legal_moves = vec![];
+            position.generate_moves(&mut legal_moves);
+            let mv = legal_moves[(ply * 7) % legal_moves.len()].clone();
+            moves.push(position.move_to_lan(&mv));
+            position.do_move(mv);
+        }
+        self.check(&format!("{}s position startpos moves", S), |engine| {
+            new_game(engine, S, timeout)?;
+            search(
+                engine,
+                &position,
+                &format!("position startpos moves {}", moves.join(" ")),
+                &clock_go_string(SEARCH_TIME, SEARCH_INCREMENT),
+                SEARCH_TIME + timeout,
+            )
+        });
+        self.check(&format!("{}s position tps", S), |engine| {
+            new_game(engine, S, timeout)?;
+            search(
+                engine,
+                &position,
+                &format!("position tps {}", position.to_fen()),
+                &clock_go_string(SEARCH_TIME, SEARCH_INCREMENT),
+                SEARCH_TIME + timeout,
+            )
+        });
+    }
+
+    fn check_komi(&mut self) {
+        let check_name = format!("HalfKomi {}", HALF_KOMI);
+        let half_komi_option = self
+            .options
+            .iter()
+            .find(|option| option.name == "HalfKomi")
+            .cloned();
+        let default_half_komi = match half_komi_option {
+            Some(UciOption {
+                option_type: UciOptionType::Spin(default, min, max),
+                ..
+            }) if (min..=max).contains(&(HALF_KOMI as i64)) => default,
+            Some(option) => {
+                self.record(
+                    &check_name,
+                    Err(format!(
+                        "Expected a spin option including {}, got {:?}",
+                        HALF_KOMI, option.option_type
+                    )),
+                );
+                return;
+            }
+            None => {
+                self.results.push(CheckResult {
+                    name: check_name,
+                    outcome: CheckOutcome::Skipped(
+                        "No HalfKomi option, engine only supports 0 komi".to_string(),
+                    ),
+                });
+                return;
+            }
+        };
+        let timeout = self.timeout;
+        self.check(&check_name, |engine| {
+            write_line(
+                engine,
+                &format!("setoption name HalfKomi value {}", HALF_KOMI),
+            )?;
+            new_game(engine, 6, timeout)?;
+            let result = search(
+                engine,
+                &Position::<6>::start_position_with_komi(Komi::from_half_komi(HALF_KOMI).unwrap()),
+                "position startpos",
+                &clock_go_string(SEARCH_TIME, SEARCH_INCREMENT),
+                SEARCH_TIME + timeout,
+            )?;
+            write_line(
+                engine,
+                &format!("setoption name HalfKomi value {}", default_half_komi),
+            )?;
+            is_ready(engine, timeout)?;
+            Ok(result)
+        });
+    }
+
+    /// Search with very little time on the clock. The engine must move before its time runs out
+    fn check_time_pressure(&mut self) {
+        let timeout = self.timeout;
+        self.check("Time pressure", |engine| {
+            new_game(engine, 5, timeout)?;
+            let start_time = Instant::now();
+            search(
+                engine,
+                &Position::<5>::start_position(),
+                "position startpos",
+                &clock_go_string(LOW_TIME, Duration::ZERO),
+                LOW_TIME + timeout,
+            )?;
+            let time_taken = start_time.elapsed();
+            if time_taken > LOW_TIME {
+                Err(format!(
+                    "Took {:.2}s with {:.2}s on the clock",
+                    time_taken.as_secs_f32(),
+                    LOW_TIME.as_secs_f32()
+                ))
+            } else {
+                Ok(format!("Took {:.3}s", time_taken.as_secs_f32()))
+            }
+        });
+    }
+
+    fn check_quit(&mut self) {
+        let outcome = match self.engine.as_mut() {
+            Some(engine) => match engine.shutdown() {
+                Ok(exit_status) if exit_status.success() => Ok(String::new()),
+                Ok(exit_status) => Err(format!("Engine did not exit cleanly, {}", exit_status)),
+                Err(err) => Err(err.to_string()),
+            },
+            None => Err("Engine was not running".to_string()),
+        };
+        self.engine = None;
+        self.record("quit", outcome);
+    }
+}
+
+/// Set the option to each of a few valid values, and check that the engine is still responsive.
+/// Spin options are only set to their minimum, since large values of options like `Hash` may be expensive.
+/// The option is left at its default value
+fn check_option(
+    engine: &mut Engine,
+    option: &UciOption,
+    timeout: Duration,
+) -> Result<String, String> {
+    let name = &option.name;
+    let values: Vec<String> = match &option.option_type {
+        UciOptionType::Check(default) => vec![(!default).to_string(), default.to_string()],
+        UciOptionType::Spin(default, min, max) => {
+            if default < min || default > max {
+                return Err(format!(
+                    "Default value {} is outside of the range {} to {}",
+                    default, min, max
+                ));
+            }
+            vec![min.to_string(), default.to_string()]
+        }
+        UciOptionType::Combo(default, values) => {
+            if !values.contains(default) {
+                return Err(format!(
+                    "Default value {} is not one of the values {}",
+                    default,
+                    values.join(", ")
+                ));
+            }
+            values
+                .iter()
+                .filter(|value| *value != default)
+                .chain([default])
+                .cloned()
+                .collect()
+        }
+        UciOptionType::Button => {
+            write_line(engine, &format!("setoption name {}", name))?;
+            is_ready(engine, timeout)?;
+            return Ok("button".to_string());
+        }
+        UciOptionType::String(default) => vec![default.clone()],
+    };
+    for value in values.iter() {
+        write_line(engine, &format!("setoption name {} value {}", name, value))?;
+        is_ready(engine, timeout)
+            .map_err(|err| format!("After setting value {}: {}", value, err))?;
+    }
+    Ok(format!("{:?}", option.option_type))
+}
+
+fn clock_go_string(time: Duration, increment: Duration) -> String {
+    SearchLimits {
+        time_control: Some((time, increment)),
+        ..Default::default()
+    }
+    .go_string((time, increment), (time, increment))
+}
+
+fn new_game(engine: &mut Engine, size: usize, timeout: Duration) -> Result<(), String> {
+    write_line(engine, &format!("teinewgame {}", size))?;
+    is_ready(engine, timeout)
+}
+
+/// Search the position, and check that the engine's info lines and move are valid
+fn search<const S: usize>(
+    engine: &mut Engine,
+    position: &Position<S>,
+    position_string: &str,
+    go_string: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    write_line(engine, position_string)?;
+    write_line(engine, go_string)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let input = read_line(engine, deadline, "bestmove")?;
+        match input.split_whitespace().next() {
+            // Info lines without a score are not used by racetrack, so only those with a score are checked
+            Some("info") if input.split_whitespace().any(|word| word == "score") => {
+                if let Err(err) = parse_info_string(&input, position) {
+                    return Err(format!("Malformed info line \"{}\": {}", input.trim(), err));
+                }
+            }
+            Some("bestmove") => {
+                let move_string = input.split_whitespace().nth(1).unwrap_or_default();
+                let mv = position
+                    .move_from_lan(move_string)
+                    .map_err(|err| format!("Malformed move \"{}\": {}", move_string, err))?;
+                let mut legal_moves = vec![];
+                position.generate_moves(&mut legal_moves);
+                if !legal_moves.contains(&mv) {
+                    return Err(format!("Illegal move {}", move_string));
+                }
+                return Ok(format!("bestmove {}", move_string));
+            }
+            _ => (),
+        }
+    }
+}
+
+fn is_ready(engine: &mut Engine, timeout: Duration) -> Result<(), String> {
+    write_line(engine, "isready")?;
+    let deadline = Instant::now() + timeout;
+    while read_line(engine, deadline, "readyok")?.trim() != "readyok" {}
+    Ok(())
+}
+
+fn write_line(engine: &mut Engine, line: &str) -> Result<(), String> {
+    engine
+        .uci_write_line(line)
+        .map_err(|err| format!("Failed to send \"{}\": {}", line, err))
+}
+
+/// Read a line from the engine, while it is expected to eventually send `expected`
+fn read_line(engine: &mut Engine, deadline: Instant, expected: &str) -> Result<String, String> {
+    engine
+        .uci_read_line_with_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|err| format!("While waiting for {}: {}", expected, err))
+}
@@ -1,20 +1,40 @@
 use crate::{
+    adjudication::{AdjudicationSettings, DrawRule, ResignRule},
+    affinity::{parse_core_list, AffinityPolicy, AffinitySettings},
+    config::{self, ConfigFile},
+    engine::{SearchLimits, StderrMode},
+    game::AnnotationSettings,
+    knockout::TieBreak,
     openings::{self, BookFormat},
+    resource_limits::{parse_memory_size, ResourceLimits},
     sprt::SprtParameters,
+    spsa::SpsaSettings,
     tournament::TournamentType,
     uci::parser,
 };
-use clap::{self, Arg, ArgAction, Command};
-use std::{env, ffi::OsString, num::NonZeroUsize, process, time::Duration};
+use clap::{
+    self, builder::TypedValueParser, parser::ValueSource, Arg, ArgAction, ArgMatches, Command,
+};
+use serde::Deserialize;
+use std::{ffi::OsString, fmt, num::NonZeroUsize, str::FromStr, time::Duration};
 use tiltak::position::Komi;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct CliOptions {
     pub size: usize,
     pub concurrency: usize,
+    pub max_engines_per_worker: Option<usize>,
+    pub affinity: Option<AffinitySettings>,
     pub games: usize,
     pub engines: Vec<CliEngine>,
     pub pgnout: Option<String>,
+    pub resume: bool,
+    pub results_json: Option<String>,
+    pub report_interval: Option<usize>,
+    pub periodic_stats: bool,
+    pub fit_white_advantage: bool,
+    pub opening_report: bool,
+    pub filtered_book: Option<String>,
     pub book_path: Option<String>,
     pub book_format: openings::BookFormat,
     pub shuffle_book: bool,
@@ -23,40 +43,269 @@ pub struct CliOptions {
     pub komi: Komi,
     pub tournament_type: TournamentType,
     pub sprt: Option<SprtParameters>,
+    pub spsa: Option<SpsaSettings>,
+    pub timeout_margin: Duration,
+    pub untimed_timeout: Duration,
+    pub adjudication: AdjudicationSettings,
+    pub annotation: AnnotationSettings,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CliEngine {
     pub path: String,
-    pub cli_args: Option<String>,
-    pub time: Duration,
-    pub increment: Duration,
+    pub name: Option<String>,
+    pub cli_args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub env_vars: Vec<(String, String)>,
+    pub limits: SearchLimits,
     pub tei_settings: Vec<(String, String)>,
+    pub stderr: StderrMode,
+    pub resource_limits: ResourceLimits,
 }
 
-pub fn parse_cli_arguments() -> CliOptions {
-    parse_cli_arguments_from(&mut env::args_os()).unwrap_or_else(|err| err.exit())
+/// Options for one engine, or for all engines, from the command line or a config file.
+/// Options that are not given are `None` or empty
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineOptions {
+    path: Option<String>,
+    name: Option<String>,
+    #[serde(default, deserialize_with = "config::engine_args")]
+    arg: Vec<String>,
+    dir: Option<String>,
+    #[serde(default, deserialize_with = "config::string_table")]
+    env: Vec<(String, String)>,
+    #[serde(default, deserialize_with = "config::time_control")]
+    tc: Option<(Duration, Duration)>,
+    nodes: Option<u64>,
+    depth: Option<u32>,
+    #[serde(default, deserialize_with = "config::movetime")]
+    movetime: Option<Duration>,
+    #[serde(default, deserialize_with = "config::string_table")]
+    option: Vec<(String, String)>,
+    #[serde(default, deserialize_with = "config::from_str")]
+    stderr: Option<StderrMode>,
+    #[serde(default, deserialize_with = "config::memory_size")]
+    maxmem: Option<u64>,
+    maxcpu: Option<u64>,
 }
 
-pub fn parse_cli_arguments_from(
-    itr: impl Iterator<Item = OsString>,
-) -> Result<CliOptions, clap::Error> {
+impl EngineOptions {
+    /// Add the options for all engines. Their arguments come after the engine's own,
+    /// and any other option cannot be set both for the engine and for all engines
+    fn with_options_for_all(mut self, all: &EngineOptions, owner: &str) -> Result<Self, String> {
+        fn merge<T: Clone>(
+            key: &str,
+            owner: &str,
+            value: &mut Option<T>,
+            value_for_all: &Option<T>,
+        ) -> Result<(), String> {
+            if let Some(value_for_all) = value_for_all {
+                if value.is_some() {
+                    return Err(format!(
+                        "{} is set both for {} and for all engines",
+                        key, owner
+                    ));
+                }
+                *value = Some(value_for_all.clone());
+            }
+            Ok(())
+        }
+        merge("path", owner, &mut self.path, &all.path)?;
+        merge("name", owner, &mut self.name, &all.name)?;
+        merge("dir", owner, &mut self.dir, &all.dir)?;
+        merge("tc", owner, &mut self.tc, &all.tc)?;
+        merge("nodes", owner, &mut self.nodes, &all.nodes)?;
+        merge("depth", owner, &mut self.depth, &all.depth)?;
+        merge("movetime", owner, &mut self.movetime, &all.movetime)?;
+        merge("stderr", owner, &mut self.stderr, &all.stderr)?;
+        merge("maxmem", owner, &mut self.maxmem, &all.maxmem)?;
+        merge("maxcpu", owner, &mut self.maxcpu, &all.maxcpu)?;
+
+        self.arg.extend(all.arg.iter().cloned());
+        for (name, value) in all.option.iter() {
+            if self
+                .option
+                .iter()
+                .any(|(other, _)| other.eq_ignore_ascii_case(name))
+            {
+                return Err(format!(
+                    "tei option {} is set both for {} and for all engines",
+                    name, owner
+                ));
+            }
+            self.option.push((name.clone(), value.clone()));
+        }
+        for (name, value) in all.env.iter() {
+            if self.env.iter().any(|(other, _)| other == name) {
+                return Err(format!(
+                    "Environment variable {} is set both for {} and for all engines",
+                    name, owner
+                ));
+            }
+            self.env.push((name.clone(), value.clone()));
+        }
+        Ok(self)
+    }
+
+    /// Check that the options describe a complete engine
+    fn into_cli_engine(self, owner: &str) -> Result<CliEngine, String> {
+        let Some(path) = self.path else {
+            return Err(format!("Missing binary path for {}", owner));
+        };
+        if self
+            .option
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("HalfKomi"))
+        {
+            return Err(format!(
+                "HalfKomi cannot be set directly for {}, use the --komi argument",
+                owner
+            ));
+        }
+        if self.maxcpu == Some(0) {
+            return Err(format!("maxcpu must be at least 1 second for {}", owner));
+        }
+        let resource_limits = ResourceLimits {
+            max_memory: self.maxmem,
+            max_cpu_seconds: self.maxcpu,
+        };
+        if !resource_limits.is_empty() && !cfg!(target_os = "linux") {
+            return Err("maxmem and maxcpu are only supported on Linux".to_string());
+        }
+        Ok(CliEngine {
+            path,
+            name: self.name,
+            cli_args: self.arg,
+            working_dir: self.dir,
+            env_vars: self.env,
+            limits: SearchLimits {
+                time_control: self.tc,
+                nodes: self.nodes,
+                depth: self.depth,
+                movetime: self.movetime,
+            },
+            tei_settings: self.option,
+            stderr: self.stderr.unwrap_or_default(),
+            resource_limits,
+        })
+    }
+}
+
+/// Options for the `check-engine` subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckEngineOptions {
+    pub engine: CliEngine,
+    /// How long to wait for each response from the engine
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliCommand {
+    Tournament(CliOptions),
+    CheckEngine(CheckEngineOptions),
+}
+
+/// An invalid command line or config file
+#[derive(Debug)]
+pub enum CliError {
+    /// Rejected by clap, which formats its own message
+    Clap(clap::Error),
+    /// Rejected when checking the settings
+    Invalid(String),
+}
+
+impl From<clap::Error> for CliError {
+    fn from(err: clap::Error) -> Self {
+        CliError::Clap(err)
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Invalid(message)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Clap(err) => write!(f, "{}", err),
+            CliError::Invalid(message) => write!(f, "Error: {}", message),
+        }
+    }
+}
+
+pub fn parse_command_from(itr: impl Iterator<Item = OsString>) -> Result<CliCommand, CliError> {
+    let args: Vec<OsString> = itr.collect();
+    let matches = cli_command().try_get_matches_from(&args)?;
+    match matches.subcommand() {
+        Some(("check-engine", check_matches)) => {
+            let engine = parse_engine_options(
+                "engine #1",
+                check_matches
+                    .get_many::<String>("engine-flag")
+                    .into_iter()
+                    .flatten(),
+            )?
+            .into_cli_engine("engine #1")?;
+            if engine.limits != SearchLimits::default() {
+                return Err(CliError::Invalid("check-engine uses its own search limits, and does not take tc, nodes, depth or movetime".to_string()));
+            }
+            Ok(CliCommand::CheckEngine(CheckEngineOptions {
+                engine,
+                timeout: *check_matches.get_one::<Duration>("timeout").unwrap(),
+            }))
+        }
+        _ => parse_cli_arguments_from(args.into_iter()).map(CliCommand::Tournament),
+    }
+}
+
+/// Names of the tournament formats, for `--format`
+pub const TOURNAMENT_FORMATS: [&str; 7] = [
+    "gauntlet",
+    "round-robin",
+    "book-test",
+    "sprt",
+    "spsa",
+    "swiss",
+    "knockout",
+];
+
+/// Fields that can be added to each move's comment, for `--annotate`
+pub const ANNOTATION_FIELDS: [&str; 6] = ["nodes", "nps", "seldepth", "pv", "clock", "all"];
+
+fn cli_command() -> Command {
     let after_help: &'static str = color_print::cstr!(
         r#"<bold><underline>Per-engine options:</underline></bold>
         These options are set on each individual engine following a `--engine` argument, or to <italic>all</italic> engines following an `--all-engines` argument
 
         <bold>path=PATH</bold>
-            File path to engine binary.
+            File path to engine binary, relative to the current directory. A file name without a directory is also searched for in $PATH.
+        <bold>dir=DIRECTORY</bold>
+            Directory to run the engine in. Defaults to the current directory.
+        <bold>env.NAME=VALUE</bold>
+            Set environment variable <italic>name</italic> to <italic>value</italic> for the engine.
         <bold>tc=TC</bold>
             Time control for each game, in seconds. Format is time+increment, where the increment is optional.
+        <bold>nodes=N</bold>, <bold>depth=N</bold>, <bold>movetime=SECONDS</bold>
+            Search limits for each move. Can be used instead of, or together with, a time control. Engines without a time control never lose on time.
         <bold>arg=ARGS</bold>
-            Command-line arguments to pass to the engine.
+            Command-line arguments to pass to the engine, split into arguments like a shell would, where quotes and backslashes keep spaces in an argument. Can be given several times.
+        <bold>rawarg=ARG</bold>
+            A single command-line argument, passed to the engine as it is. Can be given several times.
         <bold>option.OPTION=VALUE</bold>
             Set tei <italic>option</italic> to <italic>value</italic> for the engine.
+        <bold>name=NAME</bold>
+            Name of the engine in the ptn output, score tables and logs. Defaults to the name the engine sends with `id name`, or the binary path. Engines with the same name are numbered.
+        <bold>stderr=MODE</bold>
+            Where to send the engine's stderr output. <italic>inherit</italic> (the default) echoes it to racetrack's stderr, <italic>log</italic> writes it to the --log file, and <italic>file</italic> or <italic>file:DIRECTORY</italic> writes it to a separate file for each engine and worker thread.
+        <bold>maxmem=SIZE</bold>, <bold>maxcpu=SECONDS</bold>
+            Limit the engine's memory, like 512M or 4G, and its cpu time in each game. An engine that exceeds a limit forfeits the game, and is restarted. Only supported on Linux.
         "#
     );
 
-    let matches = Command::new("Racetrack")
+    Command::new("Racetrack")
         .after_help(after_help)
         .version("0.2.1")
         .author("Morten Lohne")
@@ -89,17 +338,54 @@ pub fn parse_cli_arguments_from(
             .long("concurrency")
             .value_name("n")
             .value_parser(clap::value_parser!(u64).range(1..=1024)))
+        .arg(Arg::new("max-engines-per-worker")
+            .help("Maximum number of engine processes running for each parallel game. Engines are started when a game needs them, and if the limit is reached, the engine that has been idle the longest is shut down first. By default, engines are kept running once started.")
+            .long("max-engines-per-worker")
+            .value_name("n")
+            .value_parser(clap::value_parser!(u64).range(2..)))
         .arg(Arg::new("games")
             .help("Number of games to play.")
             .short('g')
             .long("games")
-            .required(true)
+            .required_unless_present_any(["config", "rounds", "knockout-flag"])
+            .conflicts_with_all(["rounds", "knockout-flag"])
             .num_args(1)
             .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("rounds")
+            .help("Number of rounds to play in the swiss format. Each round, every engine plays a game pair, so this replaces --games.")
+            .long("rounds")
+            .value_name("n")
+            .num_args(1)
+            .value_parser(clap::value_parser!(u64).range(1..)))
         .arg(Arg::new("file")
             .help("Output file for all game PTNs.\nIf the file already exists, new games will be appended.")
             .long("ptnout")
             .num_args(1))
+        .arg(Arg::new("resume")
+            .help("Resume an interrupted tournament. Games already written to the --ptnout file are read back and not replayed.")
+            .long("resume")
+            .num_args(0)
+            .requires("file")
+            .conflicts_with("shuffle-book"))
+        .arg(Arg::new("results-json")
+            .help("Output file for machine-readable results in json format, including every game's result and the head-to-head statistics. Rewritten after every game.")
+            .long("results-json")
+            .value_name("results.json")
+            .num_args(1))
+        .arg(Arg::new("report-interval")
+            .help("Print the full results every this many games, and a one-line summary with the ETA after the other games. Defaults to every 100 games when printing to a terminal, where the summary is refreshed in place, and to every game otherwise.")
+            .long("report-interval")
+            .value_name("games")
+            .num_args(1)
+            .value_parser(clap::value_parser!(u64).range(1..)))
+        .arg(Arg::new("periodic-stats")
+            .help("Print statistics on game length, terminations, score by color and time per move with every full report. By default, they are only printed at the end of the tournament.")
+            .long("periodic-stats")
+            .num_args(0))
+        .arg(Arg::new("no-white-advantage")
+            .help("Fit the rating table without a first-move advantage for white, for example when the openings are balanced between the colors. The same ratings are used for knockout tie-breaks with tiebreak=elo.")
+            .long("no-white-advantage")
+            .num_args(0))
         .arg(Arg::new("book")
             .help("Start each game from an opening from the file. Each opening is played twice, with different colors. If there are more game pairs than openings, the openings will start to repeat. An opening file is included in the git repository.")
             .short('b')
@@ -112,7 +398,8 @@ pub fn parse_cli_arguments_from(
             .num_args(1)
             .requires("book")
             .default_value("move-list")
-            .value_parser(["move-list", "tps", "ptn"]))
+            .value_parser(clap::builder::PossibleValuesParser::new(["move-list", "tps", "ptn"])
+                .map(|format| format.parse::<BookFormat>().unwrap())))
         .arg(Arg::new("book-start")
             .long("book-start")
             .help("Start from the opening with the specified index. Starts at 1.")
@@ -120,6 +407,16 @@ pub fn parse_cli_arguments_from(
             .requires("book")
             .conflicts_with("shuffle-book")
             .value_parser(clap::value_parser!(u64).range(1..)))
+        .arg(Arg::new("opening-report")
+            .long("opening-report")
+            .help("At the end of the tournament, print each opening's score from white's perspective, and list the lopsided openings, where the same color won both games of a game pair.")
+            .num_args(0))
+        .arg(Arg::new("filtered-book")
+            .long("filtered-book")
+            .help("At the end of the tournament, write the opening book to this file without the lopsided openings, in the same format as the book.")
+            .value_name("file")
+            .num_args(1)
+            .requires("book"))
         .arg(Arg::new("shuffle-book")
             .long("shuffle-book")
             .help("Shuffle the provided opening book.")
@@ -147,114 +444,161 @@ pub fn parse_cli_arguments_from(
             .num_args(1)
             .allow_hyphen_values(true)
             .default_value("round-robin")
-            .value_parser(clap::builder::PossibleValuesParser::new(["gauntlet", "round-robin", "book-test", "sprt"]))
+            .value_parser(clap::builder::PossibleValuesParser::new(TOURNAMENT_FORMATS))
         )
+        .arg(Arg::new("timeout-margin")
+            .long("timeout-margin")
+            .help("If an engine has not moved this many seconds after its clock ran out, assume it has hung. The game is lost on time, and the engine is restarted.")
+            .value_name("seconds")
+            .num_args(1)
+            .default_value("5")
+            .value_parser(parse_timeout_margin))
+        .arg(Arg::new("untimed-timeout")
+            .long("untimed-timeout")
+            .help("If an engine without a time control or movetime, for example with a fixed depth or number of nodes, has not moved after this many seconds, assume it has hung. The game is lost on time, and the engine is restarted.")
+            .value_name("seconds")
+            .num_args(1)
+            .default_value("60")
+            .value_parser(parse_untimed_timeout))
+        .arg(Arg::new("max-plies")
+            .long("max-plies")
+            .help("Adjudicate games as drawn after this many plies, including the opening. Set to 0 for no limit.")
+            .value_name("n")
+            .num_args(1)
+            .default_value("1000")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("repetitions")
+            .long("repetitions")
+            .help("Adjudicate games as drawn when the same position occurs this many times. Set to 0 to disable.")
+            .value_name("n")
+            .num_args(1)
+            .default_value("3")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("resign-flag")
+            .long("resign")
+            .help("Adjudicate a win when both engines agree that the score is above a threshold. Takes options moves=N score=CP, where the score must be exceeded for N moves by each engine.")
+            .value_name("options")
+            .num_args(0..)
+            .action(ArgAction::Append))
+        .arg(Arg::new("draw-flag")
+            .long("draw")
+            .help("Adjudicate a draw when both engines agree that the score is close to zero. Takes options ply=M moves=N score=CP, where the score must stay within CP for N moves by each engine, after ply M.")
+            .value_name("options")
+            .num_args(0..)
+            .action(ArgAction::Append))
         .arg(Arg::new("sprt-flag")
             .long("sprt")
             .help("Perform a sequential probability ratio test.")
             .value_name("options")
             .num_args(0..)
             .action(ArgAction::Append))
-        .try_get_matches_from(itr)?;
+        .arg(Arg::new("spsa-flag")
+            .long("spsa")
+            .help("Options for SPSA tuning. Takes param.NAME=STEP for each tuned spin option, where STEP is the perturbation at the end of the run, and optionally r=LEARNING_RATE A=STABILITY alpha=ALPHA gamma=GAMMA log=FILE.")
+            .value_name("options")
+            .num_args(0..)
+            .action(ArgAction::Append))
+        .arg(Arg::new("knockout-flag")
+            .long("knockout")
+            .help("Options for the knockout format. Takes pairs=N, the number of game pairs in each match, and optionally tiebreak=extra|elo. With tiebreak=extra (the default), tied matches continue with one game pair at a time, up to N extra pairs, before falling back to elo.")
+            .value_name("options")
+            .num_args(0..)
+            .action(ArgAction::Append))
+        .arg(Arg::new("affinity-flag")
+            .long("affinity")
+            .help("Pin each parallel game, and the engine processes it starts, to cores on Linux. Takes cores=LIST, like cores=0-7,16-23, and optionally policy=game|engine. With policy=game (the default), each parallel game gets one core, shared by its engines. With policy=engine, each engine in each parallel game gets its own core. The assignment is printed at startup.")
+            .value_name("options")
+            .num_args(0..)
+            .action(ArgAction::Append))
+        .arg(Arg::new("annotate")
+            .long("annotate")
+            .help("Extra information to write in each move's comment in the ptn output, in addition to the score, depth and time taken. Takes one or more of nodes, nps, seldepth, pv and clock, or all.")
+            .value_name("fields")
+            .num_args(1..)
+            .value_parser(ANNOTATION_FIELDS))
+        .arg(Arg::new("config")
+            .long("config")
+            .help("Read tournament settings from a TOML file. Settings given on the command line override the file.")
+            .value_name("file.toml")
+            .num_args(1))
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(Command::new("check-engine")
+            .about("Check an engine for tei protocol errors, by running a series of tests against it")
+            .arg(Arg::new("engine-flag")
+                .help("The engine to check, followed by its path=PATH, and optionally arg=ARGS, option.OPTION=VALUE and name=NAME.")
+                .short('e')
+                .long("engine")
+                .value_name("options")
+                .required(true)
+                .num_args(1..))
+            .arg(Arg::new("timeout")
+                .long("timeout")
+                .help("How long to wait for each response from the engine, before the test fails.")
+                .value_name("seconds")
+                .num_args(1)
+                .default_value("10")
+                .value_parser(|input: &str| {
+                    match input.parse::<f64>() {
+                        Ok(seconds) if seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+                        _ => Err(format!("Invalid timeout \"{}\"", input)),
+                    }
+                })))
+}
 
-    let engines: Vec<CliEngine> = matches
-        .get_occurrences::<String>("engine-flag")
+pub fn parse_cli_arguments_from(
+    itr: impl Iterator<Item = OsString>,
+) -> Result<CliOptions, CliError> {
+    let matches = cli_command().try_get_matches_from(itr)?;
+
+    let config = match matches.get_one::<String>("config") {
+        Some(config_path) => config::read_config_file(config_path)
+            .map_err(|err| format!("Invalid config file \"{}\": {}", config_path, err))?,
+        None => ConfigFile::default(),
+    };
+    let on_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+    // Engines on the command line replace the engines in the config file
+    let engine_options: Vec<EngineOptions> = if on_command_line("engine-flag") {
+        matches
+            .get_occurrences::<String>("engine-flag")
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, options)| parse_engine_options(&format!("engine #{}", id + 1), options))
+            .collect::<Result<_, _>>()?
+    } else {
+        config.engines
+    };
+    let options_for_all = if on_command_line("engine-flag-all") {
+        parse_engine_options(
+            "all engines",
+            matches
+                .get_many::<String>("engine-flag-all")
+                .into_iter()
+                .flatten(),
+        )?
+    } else {
+        config.all_engines.unwrap_or_default()
+    };
+
+    let engines: Vec<CliEngine> = engine_options
         .into_iter()
-        .flatten()
         .enumerate()
-        .map(|(id, engine)| {
-            let mut engine_path = None;
-            let mut engine_arg = None;
-            let mut engine_tc_str = None;
-            let mut tei_settings: Vec<(String, String)> = vec![];
-
-            for full_arg in engine.chain(
-                matches
-                    .get_many::<String>("engine-flag-all")
-                    .into_iter()
-                    .flatten(),
-            ) {
-                if let Some((arg, value)) = full_arg.split_once('=') {
-                    if let Some(option_arg) = arg.strip_prefix("option.") {
-                        if tei_settings
-                            .iter()
-                            .any(|(a, _)| a.eq_ignore_ascii_case(option_arg))
-                        {
-                            panic!(
-                                "Duplicate value for tei argument {} for engine #{}",
-                                option_arg,
-                                id + 1
-                            )
-                        } else {
-                            assert!(!option_arg.eq_ignore_ascii_case("HalfKomi"));
-                            tei_settings.push((option_arg.to_string(), value.to_string()));
-                        }
-                    } else {
-                        match arg {
-                            "path" if engine_path.is_some() => {
-                                panic!(
-                                    "Duplicate path arguments \"{}\" and \"{}\" for engine #{}",
-                                    engine_path.unwrap(),
-                                    value,
-                                    id + 1
-                                )
-                            }
-                            "path" => engine_path = Some(value),
-                            "arg" if engine_arg.is_some() => {
-                                panic!(
-                                    "Duplicate arg arguments \"{}\" and \"{}\" for engine #{}",
-                                    engine_arg.unwrap(),
-                                    value,
-                                    id + 1
-                                )
-                            }
-                            "arg" => engine_arg = Some(value),
-                            "tc" if engine_tc_str.is_some() => {
-                                panic!(
-                                    "Duplicate tc arguments \"{}\" and \"{}\" for engine #{}",
-                                    engine_tc_str.unwrap(),
-                                    value,
-                                    id + 1
-                                )
-                            }
-                            "tc" => engine_tc_str = Some(value),
-                            _ => {
-                                eprintln!(
-                                    "Error: unknown argument {} for engine #{}",
-                                    full_arg,
-                                    id + 1
-                                );
-                                process::exit(1)
-                            }
-                        }
-                    }
-                } else {
-                    eprintln!("Error: Expected key=val, found {}", full_arg);
-                    process::exit(1)
-                }
-            }
-            let Some(path) = engine_path else {
-                eprintln!("Error: Missing binary path for engine #{}", id + 1);
-                process::exit(1)
-            };
-            let Some(tc_str) = engine_tc_str else {
-                eprintln!("Error: Missing time control for engine {}", path);
-                process::exit(1)
-            };
-            let (time, increment) = parser::parse_tc(tc_str).unwrap_or_else(|err| {
-                eprintln!("{} for engine {}", err, path);
-                process::exit(1)
-            });
-
-            CliEngine {
-                path: path.to_string(),
-                cli_args: engine_arg.map(ToString::to_string),
-                time,
-                increment,
-                tei_settings,
+        .map(|(id, options)| -> Result<CliEngine, String> {
+            let owner = format!("engine #{}", id + 1);
+            let engine = options
+                .with_options_for_all(&options_for_all, &owner)?
+                .into_cli_engine(&owner)?;
+            if engine.limits == SearchLimits::default() {
+                return Err(format!(
+                    "Missing time control or search limit for engine {}",
+                    engine.path
+                ));
             }
+            Ok(engine)
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     println!("Engines: ");
     for engine in engines.iter() {
@@ -263,45 +607,149 @@ pub fn parse_cli_arguments_from(
     println!();
 
     if engines.is_empty() {
-        eprintln!("Error: No engines added to tournament, use the --engine argument",);
-        process::exit(1);
+        return Err(invalid(
+            "No engines added to tournament, use the --engine argument",
+        ));
     }
 
-    let tournament_type = match (
-        matches.get_one::<String>("format").unwrap().as_str(),
-        engines.len(),
-    ) {
-        ("gauntlet", n @ 3..) => TournamentType::Gauntlet(NonZeroUsize::new(n - 1).unwrap()),
-        ("gauntlet", n) => {
-            eprintln!("Error: Got {} engines, at least 3 is required", n);
-            process::exit(1);
+    let log_file_name = merged(&matches, "log", config.log);
+    if log_file_name.is_none() {
+        for engine in engines.iter() {
+            if engine.stderr == StderrMode::Log {
+                eprintln!(
+                    "Warning: stderr output from engine {} is discarded, because --log is not set",
+                    engine.path
+                );
+            }
         }
+    }
+
+    let format: String = merged(&matches, "format", config.format).unwrap();
+    let rounds: Option<u64> = merged(&matches, "rounds", config.rounds);
+    let knockout = if on_command_line("knockout-flag") {
+        matches
+            .get_many::<String>("knockout-flag")
+            .map(|options| -> Result<_, String> {
+                let [pairs, tiebreak] =
+                    parse_flag_options("knockout", options, ["pairs", "tiebreak"])?;
+                let game_pairs: usize = parse_required_flag_value("knockout", "pairs", pairs)?;
+                if game_pairs == 0 {
+                    return Err("knockout pairs must be at least 1".to_string());
+                }
+                let tie_break = match tiebreak {
+                    Some(tiebreak) => {
+                        parse_required_flag_value("knockout", "tiebreak", Some(tiebreak))?
+                    }
+                    None => TieBreak::default(),
+                };
+                Ok((game_pairs, tie_break))
+            })
+            .transpose()?
+    } else {
+        config.knockout
+    };
+
+    let tournament_type = match (format.as_str(), engines.len()) {
+        ("gauntlet", n @ 3..) => TournamentType::Gauntlet(NonZeroUsize::new(n - 1).unwrap()),
         ("round-robin", n @ 2..) => TournamentType::RoundRobin(n),
-        ("round-robin", n) => {
-            eprintln!("Error: Got {} engines, at least 2 is required", n);
-            process::exit(1);
-        }
         ("book-test", n @ 1..) => TournamentType::BookTest(n),
+        ("sprt", n @ 2..) => TournamentType::Sprt(NonZeroUsize::new(n - 1).unwrap()),
+        ("spsa", 1) => TournamentType::Spsa,
+        ("swiss", n @ 3..) => {
+            let Some(rounds) = rounds else {
+                return Err(invalid("The swiss format requires the --rounds argument"));
+            };
+            TournamentType::Swiss {
+                num_engines: n,
+                rounds: rounds as usize,
+            }
+        }
+        ("knockout", n @ 2..) => {
+            let Some((game_pairs, tie_break)) = knockout else {
+                return Err(invalid(
+                    "The knockout format requires the --knockout argument",
+                ));
+            };
+            TournamentType::Knockout {
+                num_engines: n,
+                game_pairs,
+                tie_break,
+            }
+        }
+        ("gauntlet" | "swiss", n) => {
+            return Err(invalid(format!(
+                "Got {} engines, at least 3 is required",
+                n
+            )))
+        }
+        ("round-robin" | "sprt" | "knockout", n) => {
+            return Err(invalid(format!(
+                "Got {} engines, at least 2 is required",
+                n
+            )))
+        }
         ("book-test", n) => {
-            eprintln!("Error: Got {} engines, at least 1 is required", n);
-            process::exit(1);
+            return Err(invalid(format!(
+                "Got {} engines, at least 1 is required",
+                n
+            )))
         }
-        ("sprt", 2) => TournamentType::Sprt,
-        ("sprt", n) => {
-            eprintln!("Error: Got {} engines, require exactly 2", n);
-            process::exit(1);
+        ("spsa", n) => return Err(invalid(format!("Got {} engines, require exactly 1", n))),
+        (s, _) => {
+            return Err(invalid(format!(
+                "Unsupported tournament format \"{}\", expected one of {}",
+                s,
+                TOURNAMENT_FORMATS.join(", ")
+            )))
         }
-        (s, _) => panic!("Unsupported tournament format {}", s),
     };
 
-    let num_games = *matches.get_one::<usize>("games").unwrap();
+    let num_games = match tournament_type {
+        TournamentType::Swiss {
+            num_engines,
+            rounds,
+        } => {
+            // Rematches are unavoidable once every engine has played all the others.
+            // With an odd number of engines, each engine also sits out one round
+            let max_rounds = num_engines - 1 + num_engines % 2;
+            if rounds > max_rounds {
+                eprintln!(
+                    "Warning: {} rounds with {} engines, some engines will play each other more than once",
+                    rounds, num_engines
+                );
+            }
+            rounds * tournament_type.alignment()
+        }
+        // A knockout has one match less than the number of engines, not counting tie-breaks
+        TournamentType::Knockout {
+            num_engines,
+            game_pairs,
+            ..
+        } => (num_engines - 1) * game_pairs * 2,
+        _ if rounds.is_some() => {
+            return Err(invalid("--rounds is only supported for the swiss format"))
+        }
+        _ if knockout.is_some() => {
+            return Err(invalid(
+                "--knockout is only supported for the knockout format",
+            ))
+        }
+        _ => merged(&matches, "games", config.games).ok_or_else(|| {
+            invalid(
+                "Number of games not set, use the --games argument or set it in the config file",
+            )
+        })?,
+    };
 
     if num_games % tournament_type.alignment() != 0 {
         let format_name = match tournament_type {
             TournamentType::Gauntlet(_) => "gauntlet",
             TournamentType::RoundRobin(_) => "round robin",
             TournamentType::BookTest(_) => "book-test",
-            TournamentType::Sprt => "sprt",
+            TournamentType::Sprt(_) => "sprt",
+            TournamentType::Spsa => "spsa",
+            TournamentType::Swiss { .. } => "swiss",
+            TournamentType::Knockout { .. } => "knockout",
         };
         eprintln!(
             "Warning: The tournament will not give all engines an equal number of white and black games.\nFor a {} tournament with {} engines, the total number of games should be divisible by {}",
@@ -310,127 +758,555 @@ pub fn parse_cli_arguments_from(
         eprintln!();
     }
 
-    let book_format = match matches.get_one::<String>("book-format").unwrap().as_str() {
-        "move-list" => BookFormat::MoveList,
-        "tps" => BookFormat::Fen,
-        "ptn" => BookFormat::Pgn,
-        s => panic!("Unsupported book format {}", s),
-    };
+    let pgnout: Option<String> = merged(&matches, "file", config.ptnout);
+    let resume: bool = merged(&matches, "resume", config.resume).unwrap();
+    let book_path: Option<String> = merged(&matches, "book", config.book);
+    let book_start: Option<u64> = merged(&matches, "book-start", config.book_start);
+    let shuffle_book: bool = merged(&matches, "shuffle-book", config.shuffle_book).unwrap();
+    let filtered_book: Option<String> = merged(&matches, "filtered-book", config.filtered_book);
 
-    let mut sprt = None;
-    let sprt_options = matches.get_many::<String>("sprt-flag");
-    if let Some(sprt_options) = sprt_options {
-        match tournament_type {
-            TournamentType::Sprt => {}
-            _ => {
-                eprintln!(
-                    "Error: sprt option present but tournament type is {:?}",
-                    tournament_type
-                );
-                process::exit(1);
-            }
+    // Clap checks how the command-line flags depend on each other, but not the settings from the config file
+    if resume && pgnout.is_none() {
+        return Err(invalid("--resume requires a --ptnout file"));
+    }
+    if shuffle_book && (resume || book_start.is_some()) {
+        return Err(invalid(
+            "--shuffle-book cannot be used with --resume or --book-start",
+        ));
+    }
+    if book_path.is_none()
+        && (config.book_format.is_some()
+            || book_start.is_some()
+            || shuffle_book
+            || filtered_book.is_some())
+    {
+        return Err(invalid(
+            "--book-format, --book-start, --shuffle-book and --filtered-book require a --book",
+        ));
+    }
+
+    let book_format: BookFormat = merged(&matches, "book-format", config.book_format).unwrap();
+
+    let sprt = if on_command_line("sprt-flag") {
+        matches
+            .get_many::<String>("sprt-flag")
+            .map(|options| -> Result<_, String> {
+                let [elo0, elo1, alpha, beta] =
+                    parse_flag_options("sprt", options, ["elo0", "elo1", "alpha", "beta"])?;
+                sprt_parameters(
+                    parse_required_flag_value("sprt", "elo0", elo0)?,
+                    parse_required_flag_value("sprt", "elo1", elo1)?,
+                    match alpha {
+                        Some(alpha) => parse_required_flag_value("sprt", "alpha", Some(alpha))?,
+                        None => 0.05,
+                    },
+                    match beta {
+                        Some(beta) => parse_required_flag_value("sprt", "beta", Some(beta))?,
+                        None => 0.05,
+                    },
+                )
+            })
+            .transpose()?
+    } else {
+        config.sprt
+    };
+    match tournament_type {
+        TournamentType::Sprt(_) => {}
+        _ if sprt.is_some() => {
+            return Err(invalid(format!(
+                "sprt option present but tournament type is {:?}",
+                tournament_type
+            )));
         }
+        _ => {}
+    }
+
+    let spsa = if on_command_line("spsa-flag") {
+        matches
+            .get_many::<String>("spsa-flag")
+            .map(|spsa_options| -> Result<_, String> {
+                let (parameter_options, other_options): (Vec<&String>, Vec<&String>) =
+                    spsa_options.partition(|option| option.starts_with("param."));
 
-        let mut elo0 = None;
-        let mut elo1 = None;
-        let mut alpha = None;
-        let mut beta = None;
-
-        for option in sprt_options {
-            if let Some((arg, value)) = option.split_once('=') {
-                match arg {
-                    "elo0" if elo0.is_some() => panic!(
-                        "Duplicate elo0 arguments \"{}\" and \"{}\" for sprt",
-                        elo0.unwrap(),
-                        value
-                    ),
-                    "elo0" => elo0 = Some(value),
-                    "elo1" if elo1.is_some() => panic!(
-                        "Duplicate elo1 arguments \"{}\" and \"{}\" for sprt",
-                        elo1.unwrap(),
-                        value
-                    ),
-                    "elo1" => elo1 = Some(value),
-                    "alpha" if alpha.is_some() => panic!(
-                        "Duplicate alpha arguments \"{}\" and \"{}\" for sprt",
-                        alpha.unwrap(),
-                        value
-                    ),
-                    "alpha" => alpha = Some(value),
-                    "beta" if beta.is_some() => panic!(
-                        "Duplicate beta arguments \"{}\" and \"{}\" for sprt",
-                        beta.unwrap(),
-                        value
-                    ),
-                    "beta" => beta = Some(value),
-                    _ => {
-                        eprintln!("Error: unknown argument {} for sprt", option);
-                        process::exit(1)
+                let mut parameters: Vec<(String, f64)> = vec![];
+                for option in parameter_options {
+                    let Some((name, step)) = option["param.".len()..].split_once('=') else {
+                        return Err(format!("Expected param.NAME=STEP, found {}", option));
+                    };
+                    if parameters.iter().any(|(other_name, _)| other_name == name) {
+                        return Err(format!("Duplicate parameter {} for spsa", name));
                     }
+                    let step: f64 = parse_required_flag_value("spsa", option, Some(step))?;
+                    parameters.push((name.to_string(), step));
                 }
-            } else {
-                eprintln!("Error: Expected key=val, found {}", option);
-                process::exit(1)
-            }
-        }
+                check_spsa_parameters(&parameters)?;
 
-        let Some(elo0) = elo0 else {
-            eprintln!("Error: Missing elo0 for sprt");
-            process::exit(1)
-        };
-        let Some(elo1) = elo1 else {
-            eprintln!("Error: Missing elo1 for sprt");
-            process::exit(1)
-        };
-        let alpha = alpha.unwrap_or("0.05");
-        let beta = beta.unwrap_or("0.05");
-
-        let elo0 = elo0.parse::<f64>().unwrap_or_else(|err| {
-            eprintln!("{} for sprt elo0", err);
-            process::exit(1)
-        });
-        let elo1 = elo1.parse::<f64>().unwrap_or_else(|err| {
-            eprintln!("{} for sprt elo1", err);
-            process::exit(1)
-        });
-        let alpha = alpha.parse::<f64>().unwrap_or_else(|err| {
-            eprintln!("{} for sprt alpha", err);
-            process::exit(1)
-        });
-        let beta = beta.parse::<f64>().unwrap_or_else(|err| {
-            eprintln!("{} for sprt beta", err);
-            process::exit(1)
-        });
-
-        if elo0 >= elo1 {
-            eprintln!("elo1 ({}) must be greater than elo0 ({})", elo1, elo0);
-            process::exit(1)
+                let [r, stability, alpha, gamma, log] = parse_flag_options(
+                    "spsa",
+                    other_options.into_iter(),
+                    ["r", "A", "alpha", "gamma", "log"],
+                )?;
+                let mut settings = SpsaSettings::new(parameters);
+                if let Some(r) = r {
+                    settings.learning_rate = parse_required_flag_value("spsa", "r", Some(r))?;
+                }
+                if let Some(stability) = stability {
+                    settings.stability =
+                        Some(parse_required_flag_value("spsa", "A", Some(stability))?);
+                }
+                if let Some(alpha) = alpha {
+                    settings.alpha = parse_required_flag_value("spsa", "alpha", Some(alpha))?;
+                }
+                if let Some(gamma) = gamma {
+                    settings.gamma = parse_required_flag_value("spsa", "gamma", Some(gamma))?;
+                }
+                if let Some(log) = log {
+                    settings.log_path = log.to_string();
+                }
+                Ok(settings)
+            })
+            .transpose()?
+    } else {
+        config.spsa
+    };
+    match (tournament_type, &spsa) {
+        (TournamentType::Spsa, None) => {
+            return Err(invalid("The spsa format requires the --spsa argument"))
         }
-        if alpha <= 0.0 || alpha >= 0.5 {
-            eprintln!("invalid value {} for sprt alpha", alpha);
-            process::exit(1)
+        (TournamentType::Spsa, Some(_)) | (_, None) => (),
+        (_, Some(_)) => {
+            return Err(invalid(format!(
+                "spsa option present but tournament type is {:?}",
+                tournament_type
+            )))
         }
-        if beta <= 0.0 || beta >= 0.5 {
-            eprintln!("invalid value {} for sprt beta", beta);
-            process::exit(1)
+    }
+    if spsa.is_some() && resume {
+        return Err(invalid("--resume is not supported for spsa tuning"));
+    }
+    match tournament_type {
+        TournamentType::Swiss { .. } | TournamentType::Knockout { .. } if resume => {
+            return Err(invalid(format!(
+                "--resume is not supported for the {} format",
+                format
+            )))
         }
+        _ => (),
+    }
 
-        sprt = Some(SprtParameters::new(elo0, elo1, alpha, beta));
+    let resign = if on_command_line("resign-flag") {
+        matches
+            .get_many::<String>("resign-flag")
+            .map(|options| -> Result<_, String> {
+                let [moves, score] = parse_flag_options("resign", options, ["moves", "score"])?;
+                Ok(ResignRule {
+                    moves: parse_required_flag_value("resign", "moves", moves)?,
+                    score: parse_required_flag_value("resign", "score", score)?,
+                })
+            })
+            .transpose()?
+    } else {
+        config.resign
+    };
+
+    let draw = if on_command_line("draw-flag") {
+        matches
+            .get_many::<String>("draw-flag")
+            .map(|options| -> Result<_, String> {
+                let [ply, moves, score] =
+                    parse_flag_options("draw", options, ["ply", "moves", "score"])?;
+                Ok(DrawRule {
+                    start_ply: match ply {
+                        Some(ply) => parse_required_flag_value("draw", "ply", Some(ply))?,
+                        None => 0,
+                    },
+                    moves: parse_required_flag_value("draw", "moves", moves)?,
+                    score: parse_required_flag_value("draw", "score", score)?,
+                })
+            })
+            .transpose()?
+    } else {
+        config.draw
+    };
+
+    let max_plies: usize = merged(&matches, "max-plies", config.max_plies).unwrap();
+    let repetitions: usize = merged(&matches, "repetitions", config.repetitions).unwrap();
+    let adjudication = AdjudicationSettings {
+        max_plies: Some(max_plies).filter(|n| *n != 0),
+        repetitions: Some(repetitions).filter(|n| *n != 0),
+        resign,
+        draw,
+    };
+
+    let annotate = if on_command_line("annotate") {
+        matches
+            .get_many::<String>("annotate")
+            .map(|fields| fields.cloned().collect())
+    } else {
+        config.annotate
+    };
+    let annotation = match annotate {
+        Some(fields) => {
+            let has_field = |field| fields.iter().any(|other| other == field || other == "all");
+            AnnotationSettings {
+                nodes: has_field("nodes"),
+                nps: has_field("nps"),
+                seldepth: has_field("seldepth"),
+                pv: has_field("pv"),
+                clock: has_field("clock"),
+            }
+        }
+        None => AnnotationSettings::default(),
+    };
+
+    let concurrency = merged::<u64>(&matches, "concurrency", config.concurrency).unwrap() as usize;
+
+    let affinity = if on_command_line("affinity-flag") {
+        matches
+            .get_many::<String>("affinity-flag")
+            .map(|options| -> Result<_, String> {
+                let [cores, policy] = parse_flag_options("affinity", options, ["cores", "policy"])?;
+                let cores: String = parse_required_flag_value("affinity", "cores", cores)?;
+                Ok(AffinitySettings {
+                    cores: parse_core_list(&cores)
+                        .map_err(|err| format!("{} for affinity cores", err))?,
+                    policy: match policy {
+                        Some(policy) => {
+                            parse_required_flag_value("affinity", "policy", Some(policy))?
+                        }
+                        None => AffinityPolicy::default(),
+                    },
+                })
+            })
+            .transpose()?
+    } else {
+        config.affinity
+    };
+    if let Some(settings) = &affinity {
+        if !cfg!(target_os = "linux") {
+            return Err(invalid("--affinity is only supported on Linux"));
+        }
+        let cores_needed = settings.cores_needed(concurrency, engines.len());
+        if settings.cores.len() < cores_needed {
+            return Err(invalid(format!(
+                "{} cores are needed for concurrency {} with {} engines, but only {} were given",
+                cores_needed,
+                concurrency,
+                engines.len(),
+                settings.cores.len()
+            )));
+        }
     }
 
     Ok(CliOptions {
-        size: *matches.get_one::<u64>("size").unwrap() as usize,
-        concurrency: *matches.get_one::<u64>("concurrency").unwrap() as usize,
+        size: merged::<u64>(&matches, "size", config.size).unwrap() as usize,
+        concurrency,
+        max_engines_per_worker: merged::<u64>(
+            &matches,
+            "max-engines-per-worker",
+            config.max_engines_per_worker,
+        )
+        .map(|n| n as usize),
+        affinity,
         games: num_games,
         engines,
-        pgnout: matches.get_one("file").cloned(),
-        book_path: matches.get_one("book").cloned(),
+        pgnout,
+        resume,
+        results_json: merged(&matches, "results-json", config.results_json),
+        report_interval: merged::<u64>(&matches, "report-interval", config.report_interval)
+            .map(|n| n as usize),
+        periodic_stats: merged(&matches, "periodic-stats", config.periodic_stats).unwrap(),
+        fit_white_advantage: !merged(&matches, "no-white-advantage", config.no_white_advantage)
+            .unwrap(),
+        opening_report: merged(&matches, "opening-report", config.opening_report).unwrap(),
+        filtered_book,
+        book_path,
         book_format,
-        shuffle_book: *matches.get_one::<bool>("shuffle-book").unwrap(),
-        book_start_index: *matches.get_one::<u64>("book-start").unwrap_or(&1) as usize - 1,
-        log_file_name: matches.get_one::<String>("log").cloned(),
-        komi: *matches.get_one::<Komi>("komi").unwrap(),
+        shuffle_book,
+        book_start_index: book_start.unwrap_or(1) as usize - 1,
+        log_file_name,
+        komi: merged(&matches, "komi", config.komi).unwrap(),
         tournament_type,
         sprt,
+        spsa,
+        timeout_margin: merged(&matches, "timeout-margin", config.timeout_margin).unwrap(),
+        untimed_timeout: merged(&matches, "untimed-timeout", config.untimed_timeout).unwrap(),
+        adjudication,
+        annotation,
     })
 }
+
+fn invalid(message: impl Into<String>) -> CliError {
+    CliError::Invalid(message.into())
+}
+
+/// The value of a setting, where the command line overrides the config file, which overrides the default
+fn merged<T: Clone + Send + Sync + 'static>(
+    matches: &ArgMatches,
+    id: &str,
+    config_value: Option<T>,
+) -> Option<T> {
+    if matches.value_source(id) == Some(ValueSource::CommandLine) {
+        matches.get_one::<T>(id).cloned()
+    } else {
+        config_value.or_else(|| matches.get_one::<T>(id).cloned())
+    }
+}
+
+pub fn parse_timeout_margin(input: &str) -> Result<Duration, String> {
+    match input.parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("Invalid timeout margin \"{}\"", input)),
+    }
+}
+
+pub fn parse_untimed_timeout(input: &str) -> Result<Duration, String> {
+    match input.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("Invalid untimed timeout \"{}\"", input)),
+    }
+}
+
+pub fn sprt_parameters(
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+) -> Result<SprtParameters, String> {
+    if elo0 >= elo1 {
+        return Err(format!(
+            "elo1 ({}) must be greater than elo0 ({})",
+            elo1, elo0
+        ));
+    }
+    if alpha <= 0.0 || alpha >= 0.5 {
+        return Err(format!("invalid value {} for sprt alpha", alpha));
+    }
+    if beta <= 0.0 || beta >= 0.5 {
+        return Err(format!("invalid value {} for sprt beta", beta));
+    }
+    Ok(SprtParameters::new(elo0, elo1, alpha, beta))
+}
+
+pub fn check_spsa_parameters(parameters: &[(String, f64)]) -> Result<(), String> {
+    if parameters.is_empty() {
+        return Err("No parameters to tune, use param.NAME=STEP".to_string());
+    }
+    for (name, step) in parameters {
+        if step.is_nan() || *step <= 0.0 {
+            return Err(format!("Step size for {} must be positive", name));
+        }
+    }
+    Ok(())
+}
+
+/// Parse the key=value options for one engine, or for all engines. `owner` names them in error messages
+fn parse_engine_options<'a>(
+    owner: &str,
+    options: impl Iterator<Item = &'a String>,
+) -> Result<EngineOptions, String> {
+    let mut engine = EngineOptions::default();
+
+    for full_arg in options {
+        let Some((arg, value)) = full_arg.split_once('=') else {
+            return Err(format!("Expected key=val, found {}", full_arg));
+        };
+        if let Some(option_arg) = arg.strip_prefix("option.") {
+            if engine
+                .option
+                .iter()
+                .any(|(a, _)| a.eq_ignore_ascii_case(option_arg))
+            {
+                return Err(format!(
+                    "Duplicate value for tei argument {} for {}",
+                    option_arg, owner
+                ));
+            }
+            engine
+                .option
+                .push((option_arg.to_string(), value.to_string()));
+        } else if let Some(env_name) = arg.strip_prefix("env.") {
+            if env_name.is_empty() {
+                return Err(format!(
+                    "Missing environment variable name in {} for {}",
+                    full_arg, owner
+                ));
+            } else if engine.env.iter().any(|(name, _)| name == env_name) {
+                return Err(format!(
+                    "Duplicate value for environment variable {} for {}",
+                    env_name, owner
+                ));
+            }
+            engine.env.push((env_name.to_string(), value.to_string()));
+        } else {
+            match arg {
+                "path" => set_once(owner, full_arg, &mut engine.path, value.to_string())?,
+                "name" => set_once(owner, full_arg, &mut engine.name, value.to_string())?,
+                "dir" => set_once(owner, full_arg, &mut engine.dir, value.to_string())?,
+                "arg" => engine
+                    .arg
+                    .extend(parse_engine_value(owner, arg, value, split_arguments)?),
+                "rawarg" => engine.arg.push(value.to_string()),
+                "tc" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.tc,
+                    parse_engine_value(owner, arg, value, parser::parse_tc)?,
+                )?,
+                "nodes" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.nodes,
+                    parse_engine_value(owner, arg, value, str::parse)?,
+                )?,
+                "depth" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.depth,
+                    parse_engine_value(owner, arg, value, str::parse)?,
+                )?,
+                "movetime" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.movetime,
+                    parse_engine_value(owner, arg, value, parser::parse_movetime)?,
+                )?,
+                "stderr" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.stderr,
+                    parse_engine_value(owner, arg, value, str::parse)?,
+                )?,
+                "maxmem" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.maxmem,
+                    parse_engine_value(owner, arg, value, parse_memory_size)?,
+                )?,
+                "maxcpu" => set_once(
+                    owner,
+                    full_arg,
+                    &mut engine.maxcpu,
+                    parse_engine_value(owner, arg, value, str::parse)?,
+                )?,
+                _ => return Err(format!("unknown argument {} for {}", full_arg, owner)),
+            }
+        }
+    }
+    Ok(engine)
+}
+
+/// Set an engine option that can only be given once
+fn set_once<T>(
+    owner: &str,
+    full_arg: &str,
+    option: &mut Option<T>,
+    value: T,
+) -> Result<(), String> {
+    if option.is_some() {
+        return Err(format!("Duplicate argument {} for {}", full_arg, owner));
+    }
+    *option = Some(value);
+    Ok(())
+}
+
+fn parse_engine_value<T, E: fmt::Display>(
+    owner: &str,
+    key: &str,
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<T, String> {
+    parse(value).map_err(|err| format!("{} for {} {}", err, owner, key))
+}
+
+/// Split a string into command-line arguments like a POSIX shell would, but without any expansions.
+/// Single quotes keep everything inside them, double quotes allow escaping `"` and `\` with a backslash,
+/// and outside of quotes, a backslash escapes any character
+pub fn split_arguments(input: &str) -> Result<Vec<String>, String> {
+    let unclosed_quote = || format!("Unclosed quote in arguments \"{}\"", input);
+    let mut arguments = vec![];
+    // The argument being read, if any. Empty quotes are an argument too
+    let mut argument: Option<String> = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => arguments.extend(argument.take()),
+            '\'' => {
+                let argument = argument.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => argument.push(c),
+                        None => return Err(unclosed_quote()),
+                    }
+                }
+            }
+            '"' => {
+                let argument = argument.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => argument.push(c),
+                            Some(c) => {
+                                argument.push('\\');
+                                argument.push(c);
+                            }
+                            None => return Err(unclosed_quote()),
+                        },
+                        Some(c) => argument.push(c),
+                        None => return Err(unclosed_quote()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => argument.get_or_insert_with(String::new).push(c),
+                None => return Err(format!("Trailing backslash in arguments \"{}\"", input)),
+            },
+            c => argument.get_or_insert_with(String::new).push(c),
+        }
+    }
+    arguments.extend(argument);
+    Ok(arguments)
+}
+
+/// Parse key=value options for a flag, returning the value of each of the given keys, in order
+fn parse_flag_options<'a, const N: usize>(
+    flag: &str,
+    options: impl Iterator<Item = &'a String>,
+    keys: [&str; N],
+) -> Result<[Option<&'a str>; N], String> {
+    let mut values = [None; N];
+    for option in options {
+        let Some((arg, value)) = option.split_once('=') else {
+            return Err(format!("Expected key=val, found {}", option));
+        };
+        let Some(index) = keys.iter().position(|key| *key == arg) else {
+            return Err(format!("unknown argument {} for {}", option, flag));
+        };
+        if let Some(old_value) = values[index] {
+            return Err(format!(
+                "Duplicate {} arguments \"{}\" and \"{}\" for {}",
+                arg, old_value, value, flag
+            ));
+        }
+        values[index] = Some(value);
+    }
+    Ok(values)
+}
+
+fn parse_required_flag_value<T: FromStr>(
+    flag: &str,
+    key: &str,
+    value: Option<&str>,
+) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    let Some(value) = value else {
+        return Err(format!("Missing {} for {}", key, flag));
+    };
+    value
+        .parse()
+        .map_err(|err| format!("{} for {} {}", err, flag, key))
+}
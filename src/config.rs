@@ -0,0 +1,320 @@
+use crate::{
+    adjudication::{DrawRule, ResignRule},
+    affinity::{parse_core_list, AffinityPolicy, AffinitySettings},
+    cli::{self, EngineOptions},
+    knockout::TieBreak,
+    openings::BookFormat,
+    resource_limits::parse_memory_size,
+    sprt::SprtParameters,
+    spsa::SpsaSettings,
+    uci::parser,
+};
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{collections::BTreeMap, fmt, fs, str::FromStr, time::Duration};
+use tiltak::position::Komi;
+
+/// A tournament described in a TOML file, as an alternative to command-line flags.
+/// Each setting corresponds to the command-line flag with the same name, and is validated the same way,
+/// so that errors point to the setting in the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    #[serde(default, deserialize_with = "in_range::<_, 4, 8>")]
+    pub size: Option<u64>,
+    #[serde(default, deserialize_with = "in_range::<_, 1, 1024>")]
+    pub concurrency: Option<u64>,
+    #[serde(default, deserialize_with = "in_range::<_, 2, { u64::MAX }>")]
+    pub max_engines_per_worker: Option<u64>,
+    pub games: Option<usize>,
+    #[serde(default, deserialize_with = "in_range::<_, 1, { u64::MAX }>")]
+    pub rounds: Option<u64>,
+    pub ptnout: Option<String>,
+    pub resume: Option<bool>,
+    pub results_json: Option<String>,
+    #[serde(default, deserialize_with = "in_range::<_, 1, { u64::MAX }>")]
+    pub report_interval: Option<u64>,
+    pub periodic_stats: Option<bool>,
+    pub no_white_advantage: Option<bool>,
+    pub book: Option<String>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub book_format: Option<BookFormat>,
+    #[serde(default, deserialize_with = "in_range::<_, 1, { u64::MAX }>")]
+    pub book_start: Option<u64>,
+    pub shuffle_book: Option<bool>,
+    pub opening_report: Option<bool>,
+    pub filtered_book: Option<String>,
+    pub log: Option<String>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub komi: Option<Komi>,
+    #[serde(default, deserialize_with = "tournament_format")]
+    pub format: Option<String>,
+    #[serde(default, deserialize_with = "timeout_margin")]
+    pub timeout_margin: Option<Duration>,
+    #[serde(default, deserialize_with = "untimed_timeout")]
+    pub untimed_timeout: Option<Duration>,
+    pub max_plies: Option<usize>,
+    pub repetitions: Option<usize>,
+    #[serde(default, deserialize_with = "resign")]
+    pub resign: Option<ResignRule>,
+    #[serde(default, deserialize_with = "draw")]
+    pub draw: Option<DrawRule>,
+    #[serde(default, deserialize_with = "sprt")]
+    pub sprt: Option<SprtParameters>,
+    #[serde(default, deserialize_with = "spsa")]
+    pub spsa: Option<SpsaSettings>,
+    /// Number of game pairs in each match, and the tie-break
+    #[serde(default, deserialize_with = "knockout")]
+    pub knockout: Option<(usize, TieBreak)>,
+    #[serde(default, deserialize_with = "affinity")]
+    pub affinity: Option<AffinitySettings>,
+    #[serde(default, deserialize_with = "annotate")]
+    pub annotate: Option<Vec<String>>,
+    pub all_engines: Option<EngineOptions>,
+    #[serde(default)]
+    pub engines: Vec<EngineOptions>,
+}
+
+pub fn read_config_file(path: &str) -> Result<ConfigFile, String> {
+    let input = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    toml::from_str(&input).map_err(|err| err.to_string())
+}
+
+fn value_to_string(value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(format!("Unsupported value {}", value)),
+    }
+}
+
+/// Read a string, number or boolean, and parse it like the same value on the command line
+fn parse_value<'de, D, T, E>(
+    deserializer: D,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    E: fmt::Display,
+{
+    let value = toml::Value::deserialize(deserializer)?;
+    let input = value_to_string(&value).map_err(D::Error::custom)?;
+    parse(&input).map(Some).map_err(D::Error::custom)
+}
+
+pub fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_value(deserializer, str::parse)
+}
+
+pub fn time_control<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(Duration, Duration)>, D::Error> {
+    parse_value(deserializer, parser::parse_tc)
+}
+
+pub fn movetime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    parse_value(deserializer, parser::parse_movetime)
+}
+
+pub fn memory_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    parse_value(deserializer, parse_memory_size)
+}
+
+fn timeout_margin<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    parse_value(deserializer, cli::parse_timeout_margin)
+}
+
+fn untimed_timeout<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    parse_value(deserializer, cli::parse_untimed_timeout)
+}
+
+fn in_range<'de, D: Deserializer<'de>, const MIN: u64, const MAX: u64>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    let value = u64::deserialize(deserializer)?;
+    if value < MIN {
+        Err(D::Error::custom(format!("{} is less than {}", value, MIN)))
+    } else if value > MAX {
+        Err(D::Error::custom(format!(
+            "{} is greater than {}",
+            value, MAX
+        )))
+    } else {
+        Ok(Some(value))
+    }
+}
+
+fn tournament_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    if cli::TOURNAMENT_FORMATS.contains(&format.as_str()) {
+        Ok(Some(format))
+    } else {
+        Err(D::Error::custom(format!(
+            "Invalid format \"{}\", expected one of {}",
+            format,
+            cli::TOURNAMENT_FORMATS.join(", ")
+        )))
+    }
+}
+
+fn annotate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    let fields = Vec::<String>::deserialize(deserializer)?;
+    for field in fields.iter() {
+        if !cli::ANNOTATION_FIELDS.contains(&field.as_str()) {
+            return Err(D::Error::custom(format!(
+                "Invalid annotation \"{}\", expected one of {}",
+                field,
+                cli::ANNOTATION_FIELDS.join(", ")
+            )));
+        }
+    }
+    Ok(Some(fields))
+}
+
+/// Engine arguments, where a string is split like `arg=` on the command line,
+/// and each element of an array is a single argument, like `rawarg=`
+pub fn engine_args<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Arguments {
+        Split(String),
+        Raw(Vec<String>),
+    }
+    match Arguments::deserialize(deserializer) {
+        Ok(Arguments::Split(input)) => cli::split_arguments(&input).map_err(D::Error::custom),
+        Ok(Arguments::Raw(arguments)) => Ok(arguments),
+        Err(_) => Err(D::Error::custom(
+            "expected a string, or an array of strings",
+        )),
+    }
+}
+
+/// A table of names and values, like tei options or environment variables
+pub fn string_table<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    BTreeMap::<String, toml::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, value)| Ok((name, value_to_string(&value).map_err(D::Error::custom)?)))
+        .collect()
+}
+
+fn resign<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ResignRule>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        moves: usize,
+        score: i64,
+    }
+    let Table { moves, score } = Table::deserialize(deserializer)?;
+    Ok(Some(ResignRule { moves, score }))
+}
+
+fn draw<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DrawRule>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        #[serde(default)]
+        ply: usize,
+        moves: usize,
+        score: i64,
+    }
+    let Table { ply, moves, score } = Table::deserialize(deserializer)?;
+    Ok(Some(DrawRule {
+        start_ply: ply,
+        moves,
+        score,
+    }))
+}
+
+fn sprt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SprtParameters>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        elo0: f64,
+        elo1: f64,
+        alpha: Option<f64>,
+        beta: Option<f64>,
+    }
+    let table = Table::deserialize(deserializer)?;
+    cli::sprt_parameters(
+        table.elo0,
+        table.elo1,
+        table.alpha.unwrap_or(0.05),
+        table.beta.unwrap_or(0.05),
+    )
+    .map(Some)
+    .map_err(D::Error::custom)
+}
+
+fn spsa<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SpsaSettings>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        param: BTreeMap<String, f64>,
+        r: Option<f64>,
+        #[serde(rename = "A")]
+        stability: Option<f64>,
+        alpha: Option<f64>,
+        gamma: Option<f64>,
+        log: Option<String>,
+    }
+    let table = Table::deserialize(deserializer)?;
+    let mut settings = SpsaSettings::new(table.param.into_iter().collect());
+    cli::check_spsa_parameters(&settings.parameters).map_err(D::Error::custom)?;
+    settings.learning_rate = table.r.unwrap_or(settings.learning_rate);
+    settings.stability = table.stability;
+    settings.alpha = table.alpha.unwrap_or(settings.alpha);
+    settings.gamma = table.gamma.unwrap_or(settings.gamma);
+    settings.log_path = table.log.unwrap_or(settings.log_path);
+    Ok(Some(settings))
+}
+
+fn knockout<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(usize, TieBreak)>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        pairs: usize,
+        #[serde(default, deserialize_with = "from_str")]
+        tiebreak: Option<TieBreak>,
+    }
+    let table = Table::deserialize(deserializer)?;
+    if table.pairs == 0 {
+        return Err(D::Error::custom("knockout pairs must be at least 1"));
+    }
+    Ok(Some((table.pairs, table.tiebreak.unwrap_or_default())))
+}
+
+fn affinity<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<AffinitySettings>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Table {
+        cores: toml::Value,
+        #[serde(default, deserialize_with = "from_str")]
+        policy: Option<AffinityPolicy>,
+    }
+    let table = Table::deserialize(deserializer)?;
+    let cores = value_to_string(&table.cores)
+        .and_then(|cores| parse_core_list(&cores))
+        .map_err(D::Error::custom)?;
+    Ok(Some(AffinitySettings {
+        cores,
+        policy: table.policy.unwrap_or_default(),
+    }))
+}
@@ -1,76 +1,158 @@
+#[cfg(target_os = "linux")]
+use crate::affinity;
+use crate::resource_limits::{self, ResourceLimit, ResourceLimits};
 use crate::uci::parser::parse_option;
 use crate::uci::{UciOption, UciOptionType};
 use log::{debug, info, warn};
+use std::fmt;
+use std::fmt::Write as _;
 use std::io;
 use std::io::Result;
 use std::io::{BufRead, BufReader, Write};
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
-use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::str::FromStr;
 use std::string::ToString;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::time::Duration;
-use std::{env, thread};
+use std::{env, fs, thread};
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct EngineBuilder {
+    /// Path to the binary, relative to racetrack's current directory. A bare file name may also be found in `$PATH`
     pub path: String,
-    pub args: Option<String>,
+    /// Name to use instead of the binary path
+    pub name: Option<String>,
+    /// Command-line arguments, passed to the engine as they are
+    pub args: Vec<String>,
+    /// Directory to run the engine in, instead of racetrack's current directory
+    pub working_dir: Option<String>,
+    /// Environment variables to set for the engine, in addition to racetrack's own
+    pub env_vars: Vec<(String, String)>,
     pub desired_uci_options: Vec<(String, String)>,
-    pub game_time: Duration,
-    pub increment: Duration,
+    pub limits: SearchLimits,
+    /// How long past its remaining time an engine may take to respond, before it is considered hung
+    pub timeout_margin: Duration,
+    /// How long an engine without a clock or movetime, like a fixed-depth search, may take to move
+    pub untimed_timeout: Duration,
+    pub stderr: StderrMode,
+    /// Name of the engine's stderr file, if different from its name.
+    /// Set for tournaments, so that the file doesn't change when the engine is renamed to the name it reports
+    pub stderr_file_name: Option<String>,
+    /// The worker thread that the engine belongs to, to tell apart the stderr output of different workers
+    pub worker_id: Option<usize>,
+    /// Pin the engine process to this core. Only supported on Linux
+    pub cpu_core: Option<usize>,
+    pub resource_limits: ResourceLimits,
 }
 
-impl EngineBuilder {
-    /// Initialize the engine, including starting the binary and reading the engine's available uci commands.
-    pub fn init(&self) -> Result<Engine> {
-        // TODO: Error for not permission to current directory
-        let mut absolute_path = env::current_dir()?;
-        absolute_path.push(&self.path);
+/// Where to send the engine's stderr output
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum StderrMode {
+    /// Echo to racetrack's own stderr
+    #[default]
+    Inherit,
+    /// Write to the debug log, tagged with the engine's name and worker
+    Log,
+    /// Write to a file in this directory, with a separate file for each engine and worker
+    File(String),
+}
 
-        // TODO: More helpful error message if engine binary is not found.
-        // For example, print contents of directory searched?
+impl FromStr for StderrMode {
+    type Err = String;
 
-        // On Unix, set process_group(0) on each engine, to give each its own process group
-        // Otherwise, Ctrl-C from a terminal would propagate to the engines and immediately kill them, even if we caught the signal
-        // More details https://mywiki.wooledge.org/SignalTrap
-        #[cfg(unix)]
-        let mut child = match &self.args {
-            Some(args) => Command::new(&absolute_path)
-                .process_group(0)
-                .args(args.split_whitespace())
-                .stdout(Stdio::piped())
-                .stdin(Stdio::piped())
-                .spawn()?,
-            None => Command::new(&absolute_path)
-                .process_group(0)
-                .stdout(Stdio::piped())
-                .stdin(Stdio::piped())
-                .spawn()?,
-        };
-        #[cfg(not(unix))]
-        let mut child = match &self.args {
-            Some(args) => Command::new(&absolute_path)
-                .args(args.split_whitespace())
-                .stdout(Stdio::piped())
-                .stdin(Stdio::piped())
-                .spawn()?,
-            None => Command::new(&absolute_path)
-                .stdout(Stdio::piped())
-                .stdin(Stdio::piped())
-                .spawn()?,
-        };
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "inherit" => Ok(StderrMode::Inherit),
+            "log" => Ok(StderrMode::Log),
+            "file" => Ok(StderrMode::File(".".to_string())),
+            _ => match input.strip_prefix("file:") {
+                Some(directory) if !directory.is_empty() => {
+                    Ok(StderrMode::File(directory.to_string()))
+                }
+                _ => Err(format!(
+                    "Invalid stderr mode \"{}\", expected inherit, log, file or file:DIRECTORY",
+                    input
+                )),
+            },
+        }
+    }
+}
 
-        let stdout = BufReader::new(child.stdout.take().unwrap());
-        let stdin = child.stdin.take().unwrap();
+/// Limits for each of an engine's searches. Any combination can be set, but at least one must be
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SearchLimits {
+    /// Time and increment for the whole game. Engines without a time control never lose on time
+    pub time_control: Option<(Duration, Duration)>,
+    pub nodes: Option<u64>,
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+}
 
-        let mut engine = Engine {
-            child,
-            stdout,
-            stdin,
-            name: self.path.to_string(),
-            builder: self.clone(),
-            options: vec![],
-        };
+impl SearchLimits {
+    /// The `go` command for a search, given both players' remaining time.
+    /// The clock values are only sent if the engine has a time control
+    pub fn go_string(
+        &self,
+        (white_time, white_inc): (Duration, Duration),
+        (black_time, black_inc): (Duration, Duration),
+    ) -> String {
+        let mut go_string = "go".to_string();
+        if self.time_control.is_some() {
+            write!(
+                go_string,
+                " wtime {} btime {} winc {} binc {}",
+                white_time.as_millis(),
+                black_time.as_millis(),
+                white_inc.as_millis(),
+                black_inc.as_millis(),
+            )
+            .unwrap();
+        }
+        if let Some(nodes) = self.nodes {
+            write!(go_string, " nodes {}", nodes).unwrap();
+        }
+        if let Some(depth) = self.depth {
+            write!(go_string, " depth {}", depth).unwrap();
+        }
+        if let Some(movetime) = self.movetime {
+            write!(go_string, " movetime {}", movetime.as_millis()).unwrap();
+        }
+        go_string
+    }
+}
+
+/// Human-readable description, for the `Clock` tag
+impl fmt::Display for SearchLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some((time, increment)) = self.time_control {
+            parts.push(format!(
+                "{}:{} +{:.1}",
+                time.as_secs() / 60,
+                time.as_secs() % 60,
+                increment.as_secs_f32()
+            ));
+        }
+        if let Some(nodes) = self.nodes {
+            parts.push(format!("{} nodes", nodes));
+        }
+        if let Some(depth) = self.depth {
+            parts.push(format!("depth {}", depth));
+        }
+        if let Some(movetime) = self.movetime {
+            parts.push(format!("{:.1}s/move", movetime.as_secs_f32()));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl EngineBuilder {
+    /// Initialize the engine, including starting the binary and reading the engine's available uci commands.
+    pub fn init(&self) -> Result<Engine> {
+        let mut engine = self.spawn()?;
 
         engine.uci_write_line("tei")?;
 
@@ -83,10 +165,27 @@ impl EngineBuilder {
                 Some("option") => {
                     engine.options.push(parse_option(&input).unwrap()); // TODO: Handle error
                 }
+                Some("id") => {
+                    let mut words = input.trim().splitn(3, char::is_whitespace).skip(1);
+                    match (words.next(), words.next()) {
+                        (Some("name"), Some(name)) => {
+                            engine.id_name = Some(name.trim().to_string())
+                        }
+                        (Some("author"), Some(author)) => {
+                            engine.author = Some(author.trim().to_string())
+                        }
+                        _ => info!("Unexpected message \"{}\", ignoring", input.trim()),
+                    }
+                }
                 s => info!("Unexpected message \"{}\", ignoring", s.unwrap_or_default()),
             }
         }
 
+        // Use the engine's own name, unless a name was set explicitly
+        if let (None, Some(id_name)) = (&self.name, &engine.id_name) {
+            engine.name = id_name.clone();
+        }
+
         // If engine has no HalfKomi option, assume it only supports 0 komi
         // Options at their default value are never sent to the engines
         if !engine
@@ -103,15 +202,220 @@ impl EngineBuilder {
 
         Ok(engine)
     }
+
+    /// Start the engine binary, without sending any commands to it
+    pub fn spawn(&self) -> Result<Engine> {
+        let absolute_path = self.resolve_path()?;
+
+        let mut command = Command::new(&absolute_path);
+        command.args(&self.args);
+        if let Some(working_dir) = &self.working_dir {
+            if !Path::new(working_dir).is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Working directory \"{}\" does not exist", working_dir),
+                ));
+            }
+            command.current_dir(working_dir);
+        }
+        command.envs(self.env_vars.iter().map(|(name, value)| (name, value)));
+        // On Unix, set process_group(0) on each engine, to give each its own process group
+        // Otherwise, Ctrl-C from a terminal would propagate to the engines and immediately kill them, even if we caught the signal
+        // More details https://mywiki.wooledge.org/SignalTrap
+        #[cfg(unix)]
+        command.process_group(0);
+        #[cfg(target_os = "linux")]
+        if let Some(core) = self.cpu_core {
+            // Safety: Setting the affinity is a single system call, with no allocation or locking
+            unsafe {
+                command.pre_exec(move || affinity::set_thread_affinity(&[core]));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if !self.resource_limits.is_empty() {
+            let limits = self.resource_limits;
+            // Safety: Setting the limits only makes system calls, with no allocation or locking
+            unsafe {
+                command.pre_exec(move || resource_limits::apply(&limits));
+            }
+        }
+        let stderr = match self.stderr {
+            StderrMode::Inherit => Stdio::inherit(),
+            StderrMode::Log | StderrMode::File(_) => Stdio::piped(),
+        };
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stdin(Stdio::piped())
+            .stderr(stderr)
+            .spawn()?;
+
+        let stdout = spawn_stdout_reader(child.stdout.take().unwrap(), &self.path)?;
+        let stdin = child.stdin.take().unwrap();
+        if let Some(stderr) = child.stderr.take() {
+            self.spawn_stderr_reader(stderr)?;
+        }
+
+        Ok(Engine {
+            child,
+            stdout,
+            stdin,
+            name: self.display_name().to_string(),
+            builder: self.clone(),
+            options: vec![],
+            id_name: None,
+            author: None,
+        })
+    }
+}
+
+impl EngineBuilder {
+    fn display_name(&self) -> &str {
+        self.name.as_ref().unwrap_or(&self.path)
+    }
+
+    /// Find the engine binary. The path is relative to racetrack's current directory, even if the engine has its own working directory.
+    /// A bare file name that isn't found there is searched for in each directory of `$PATH`
+    fn resolve_path(&self) -> Result<PathBuf> {
+        // TODO: Error for not permission to current directory
+        let current_dir = env::current_dir()?;
+        // On Windows, the binary may be given without its .exe extension
+        let find_in = |directory: &Path| -> Option<PathBuf> {
+            let path = directory.join(&self.path);
+            if path.is_file() {
+                return Some(path);
+            }
+            let path = directory.join(format!("{}{}", self.path, env::consts::EXE_SUFFIX));
+            Some(path).filter(|path| path.is_file())
+        };
+        if let Some(path) = find_in(&current_dir) {
+            return Ok(path);
+        }
+
+        let is_bare_name =
+            Path::new(&self.path).components().count() == 1 && !Path::new(&self.path).is_absolute();
+        let search_path: Vec<PathBuf> = match env::var_os("PATH") {
+            Some(path_var) if is_bare_name => env::split_paths(&path_var).collect(),
+            _ => vec![],
+        };
+        if let Some(path) = search_path.iter().find_map(|directory| find_in(directory)) {
+            return Ok(path);
+        }
+
+        let mut message = format!(
+            "Engine binary \"{}\" not found in {}",
+            self.path,
+            current_dir.display()
+        );
+        if !search_path.is_empty() {
+            let directories: Vec<String> = search_path
+                .iter()
+                .map(|directory| directory.display().to_string())
+                .collect();
+            write!(message, ", or in $PATH: {}", directories.join(", ")).unwrap();
+        }
+        Err(io::Error::new(io::ErrorKind::NotFound, message))
+    }
+
+    /// Forward the engine's stderr to the debug log or to a file, from a separate thread.
+    /// The thread exits when the engine closes its stderr
+    fn spawn_stderr_reader(&self, stderr: ChildStderr) -> Result<()> {
+        let worker = self
+            .worker_id
+            .map(|id| format!("#{}", id))
+            .unwrap_or_default();
+        let mut file = match &self.stderr {
+            StderrMode::File(directory) => {
+                fs::create_dir_all(directory)?;
+                // Engine names may be file paths, which cannot be used in a file name
+                let file_name: String = self
+                    .stderr_file_name
+                    .as_deref()
+                    .unwrap_or_else(|| self.display_name())
+                    .chars()
+                    .map(|c| {
+                        if c.is_alphanumeric() || c == '-' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                let mut path = PathBuf::from(directory);
+                if worker.is_empty() {
+                    path.push(format!("{}.stderr.log", file_name));
+                } else {
+                    path.push(format!("{}_{}.stderr.log", file_name, worker));
+                }
+                Some(
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)?,
+                )
+            }
+            _ => None,
+        };
+        let name = self.display_name().to_string();
+        thread::Builder::new()
+            .name(format!("{} stderr", self.path))
+            .spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    match file.as_mut() {
+                        Some(file) => {
+                            let timestamp = chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S%.3f]");
+                            if writeln!(file, "{} {}", timestamp, line).is_err() {
+                                break;
+                            }
+                        }
+                        None => debug!("! {} {}: {}", name, worker, line),
+                    }
+                }
+            })?;
+        Ok(())
+    }
+}
+
+/// Read the engine's stdout from a separate thread, so that reads can time out if the engine hangs.
+/// The thread exits when the engine closes its stdout, which drops the sender.
+fn spawn_stdout_reader(stdout: ChildStdout, name: &str) -> Result<Receiver<Result<String>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name(format!("{} stdout", name))
+        .spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let mut input = String::new();
+                match stdout.read_line(&mut input) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if sender.send(Ok(input)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = sender.send(Err(err));
+                        break;
+                    }
+                }
+            }
+        })?;
+    Ok(receiver)
 }
 
 pub struct Engine {
     child: Child,
-    stdout: BufReader<ChildStdout>,
+    stdout: Receiver<Result<String>>,
     stdin: ChildStdin,
     name: String,
     builder: EngineBuilder,
     options: Vec<UciOption>,
+    /// Name sent by the engine with `id name`
+    id_name: Option<String>,
+    /// Author sent by the engine with `id author`
+    author: Option<String>,
 }
 
 impl Engine {
@@ -119,12 +423,27 @@ impl Engine {
         &self.name
     }
 
+    /// Rename the engine. The name is kept if the engine is restarted
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+        self.builder.name = Some(name.to_string());
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
     pub fn builder(&self) -> &EngineBuilder {
         &self.builder
     }
 
     pub fn uci_read_line(&mut self) -> Result<String> {
-        self.read_line()
+        self.read_line(None)
+    }
+
+    /// Read a line from the engine, or return an error of kind `TimedOut` if the engine does not respond in time
+    pub fn uci_read_line_with_timeout(&mut self, timeout: Duration) -> Result<String> {
+        self.read_line(Some(timeout))
     }
 
     pub fn uci_write_line(&mut self, line: &str) -> Result<()> {
@@ -139,22 +458,31 @@ impl Engine {
         Ok(())
     }
 
-    fn read_line(&mut self) -> Result<String> {
-        let mut input = String::new();
-        if self.stdout.read_line(&mut input)? == 0 {
-            Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "Read 0 bytes from engine",
-            ))
-        } else {
-            debug!(
-                "< {} {}: {}",
-                self.name,
-                thread::current().name().unwrap_or_default(),
-                input.trim()
-            );
-            Ok(input)
-        }
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String> {
+        let eof_error = || io::Error::new(io::ErrorKind::UnexpectedEof, "Read 0 bytes from engine");
+        let input = match timeout {
+            Some(timeout) => match self.stdout.recv_timeout(timeout) {
+                Ok(input) => input?,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "No response from engine after {:.1}s",
+                            timeout.as_secs_f32()
+                        ),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(eof_error()),
+            },
+            None => self.stdout.recv().map_err(|_| eof_error())??,
+        };
+        debug!(
+            "< {} {}: {}",
+            self.name,
+            thread::current().name().unwrap_or_default(),
+            input.trim()
+        );
+        Ok(input)
     }
 
     pub fn do_isready_sync(&mut self) -> Result<()> {
@@ -201,6 +529,10 @@ impl Engine {
         }
     }
 
+    pub fn option(&self, name: &str) -> Option<&UciOption> {
+        self.options.iter().find(|option| option.name == name)
+    }
+
     pub fn supports_option_value(&self, name: &str, value: &str) -> bool {
         if let Some(option) = self.options.iter().find(|option| option.name == name) {
             option.option_type.value_is_supported(value)
@@ -209,11 +541,35 @@ impl Engine {
         }
     }
 
+    /// Before each game, give the engine its full cpu time limit again
+    pub fn extend_cpu_limit(&self) {
+        if let Some(max_cpu_seconds) = self.builder.resource_limits.max_cpu_seconds {
+            if let Err(err) = resource_limits::extend_cpu_limit(self.child.id(), max_cpu_seconds) {
+                warn!("Failed to set the cpu time limit of {}: {}", self.name, err);
+            }
+        }
+    }
+
+    /// After the engine has disconnected, check whether it was stopped for exceeding one of its resource limits
+    pub fn exceeded_resource_limit(&mut self) -> Option<ResourceLimit> {
+        if self.builder.resource_limits.is_empty() {
+            return None;
+        }
+        // The engine may close its output slightly before exiting
+        for _ in 0..10 {
+            if let Ok(Some(exit_status)) = self.child.try_wait() {
+                return resource_limits::exceeded_limit(exit_status, &self.builder.resource_limits);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        None
+    }
+
     /// Restart the engine from scratch
     pub fn restart(&mut self) -> Result<()> {
         self.shutdown()?;
         *self = self.builder.init()?;
-        Ok(())
+        self.set_options_from_builder()
     }
 
     /// Shuts down the engine process. If the engine does not respond to a `quit` command, kill it.
@@ -1,14 +1,18 @@
+use crate::adjudication::{AdjudicationSettings, Adjudicator};
 use crate::engine::Engine;
 use crate::openings::Opening;
+use crate::resource_limits::ResourceLimit;
 use crate::tournament::{EngineId, Worker};
 use crate::uci::parser::parse_info_string;
-use crate::uci::UciInfo;
+use crate::uci::{Score, ScoreBound, UciInfo};
 use board_game_traits::Color;
 use chrono::{Datelike, Local};
 use log::{error, warn};
 use pgn_traits::PgnPosition;
+use std::fmt;
 use std::fmt::Write;
-use std::time::Instant;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::{io, thread};
 use tiltak::position::Komi;
 use tiltak::ptn::{Game, PtnMove};
@@ -22,6 +26,150 @@ pub struct ScheduledGame<B: PgnPosition> {
     pub size: usize,
 }
 
+/// Extra information to write in each move's comment, in addition to the score, depth and time taken
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnnotationSettings {
+    pub nodes: bool,
+    pub nps: bool,
+    pub seldepth: bool,
+    /// The engine's principal variation, as PTN moves
+    pub pv: bool,
+    /// Both sides' remaining time after the move, for sides with a time control
+    pub clock: bool,
+}
+
+/// Reason for a game ending, other than a regular win or draw. Written to the `Termination` tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// The engine with this color ran out of time, or stopped responding
+    TimeForfeit(Color),
+    Disconnect(Color),
+    MalformedMove(Color),
+    IllegalMove(Color),
+    /// The engine with this color was stopped for exceeding one of its resource limits.
+    /// For the memory limit, this is a guess from the engine crashing
+    ResourceLimit(Color, ResourceLimit),
+    /// Contains the number of plies played
+    MoveLimit(usize),
+    /// Contains the number of repetitions required
+    Repetition(usize),
+    /// The engine with this color was adjudicated lost, after both engines agreed on the score
+    Resignation(Color),
+    ScoreDraw,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Termination::TimeForfeit(color) => write!(f, "{} wins on time", !*color),
+            Termination::Disconnect(color) => write!(f, "{} disconnected or crashed", color),
+            Termination::MalformedMove(color) => write!(f, "{} sent a malformed move", color),
+            Termination::IllegalMove(color) => write!(f, "{} made an illegal move", color),
+            Termination::ResourceLimit(color, limit) => {
+                write!(f, "{} {}", color, limit.exceeded_description())
+            }
+            Termination::MoveLimit(plies) => {
+                write!(f, "Adjudicated as a draw after reaching {} plies", plies)
+            }
+            Termination::Repetition(repetitions) => {
+                write!(
+                    f,
+                    "Adjudicated as a draw by {}-fold repetition",
+                    repetitions
+                )
+            }
+            Termination::Resignation(color) => write!(
+                f,
+                "Adjudicated as a win for {}, both engines agreed on the score",
+                !*color
+            ),
+            Termination::ScoreDraw => {
+                write!(f, "Adjudicated as a draw, both engines agreed on the score")
+            }
+        }
+    }
+}
+
+/// Parse the text of a game's `Termination` tag, as written by `Display`.
+/// Also accepts the move limit wording of earlier versions, "Game terminated after reaching {moves} moves."
+impl FromStr for Termination {
+    type Err = String;
+
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Unknown termination \"{}\"", tag);
+        let parse_color = |word: &str| match word {
+            "White" => Ok(Color::White),
+            "Black" => Ok(Color::Black),
+            _ => Err(error()),
+        };
+        let parse_number = |word: &str| {
+            // Don't accept signs or whitespace, which `parse` would otherwise allow
+            if word.bytes().all(|byte| byte.is_ascii_digit()) {
+                word.parse::<usize>().map_err(|_| error())
+            } else {
+                Err(error())
+            }
+        };
+
+        if tag == "Adjudicated as a draw, both engines agreed on the score" {
+            return Ok(Termination::ScoreDraw);
+        }
+        if let Some(rest) = tag.strip_prefix("Adjudicated as a win for ") {
+            let winner = rest
+                .strip_suffix(", both engines agreed on the score")
+                .ok_or_else(error)?;
+            return Ok(Termination::Resignation(!parse_color(winner)?));
+        }
+        if let Some(rest) = tag.strip_prefix("Adjudicated as a draw after reaching ") {
+            let plies = rest.strip_suffix(" plies").ok_or_else(error)?;
+            return Ok(Termination::MoveLimit(parse_number(plies)?));
+        }
+        if let Some(rest) = tag.strip_prefix("Game terminated after reaching ") {
+            let moves = rest.strip_suffix(" moves.").ok_or_else(error)?;
+            // Earlier versions wrote the number of full moves, so this is the number of plies rounded down to even
+            return Ok(Termination::MoveLimit(parse_number(moves)? * 2));
+        }
+        if let Some(rest) = tag.strip_prefix("Adjudicated as a draw by ") {
+            let repetitions = rest.strip_suffix("-fold repetition").ok_or_else(error)?;
+            return Ok(Termination::Repetition(parse_number(repetitions)?));
+        }
+
+        // The remaining terminations start with a color
+        let (color, rest) = tag.split_once(' ').ok_or_else(error)?;
+        let color = parse_color(color)?;
+        match rest {
+            "wins on time" => Ok(Termination::TimeForfeit(!color)),
+            "disconnected or crashed" => Ok(Termination::Disconnect(color)),
+            "sent a malformed move" => Ok(Termination::MalformedMove(color)),
+            "made an illegal move" => Ok(Termination::IllegalMove(color)),
+            "crashed, likely from exceeding its memory limit" => {
+                Ok(Termination::ResourceLimit(color, ResourceLimit::Memory))
+            }
+            "exceeded its cpu time limit" => {
+                Ok(Termination::ResourceLimit(color, ResourceLimit::CpuTime))
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+impl Termination {
+    /// Short name for the kind of termination, for the game statistics
+    pub fn kind(self) -> &'static str {
+        match self {
+            Termination::TimeForfeit(_) => "time",
+            Termination::Disconnect(_) => "crash",
+            Termination::MalformedMove(_) => "malformed",
+            Termination::IllegalMove(_) => "illegal",
+            Termination::ResourceLimit(_, _) => "limit",
+            Termination::MoveLimit(_) => "max plies",
+            Termination::Repetition(_) => "repetition",
+            Termination::Resignation(_) => "resign",
+            Termination::ScoreDraw => "score draw",
+        }
+    }
+}
+
 fn forfeit_win_str(color: Color) -> &'static str {
     match color {
         Color::White => "1-0",
@@ -34,6 +182,8 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
         self,
         worker: &mut Worker,
         position_settings: &B::Settings,
+        adjudication: &AdjudicationSettings,
+        annotation: &AnnotationSettings,
     ) -> io::Result<Game<B>> {
         let mut position =
             B::from_fen_with_settings(&self.opening.root_position.to_fen(), position_settings)
@@ -52,46 +202,62 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             })
             .collect();
 
+        let mut adjudicator = Adjudicator::new(adjudication, &position);
+
         for PtnMove { mv, .. } in moves.iter() {
             position.do_move(mv.clone());
+            adjudicator.add_position(&position, None);
         }
 
-        worker.engines[white].uci_write_line(&format!("teinewgame {}", self.size))?;
-        worker.engines[white].uci_write_line("isready")?;
+        worker.engine(white).extend_cpu_limit();
+        worker
+            .engine(white)
+            .uci_write_line(&format!("teinewgame {}", self.size))?;
+        worker.engine(white).uci_write_line("isready")?;
 
         // White and black can be the same engine for the book-analysis tournament format
         if self.white_engine_id != self.black_engine_id {
-            worker.engines[black].uci_write_line(&format!("teinewgame {}", self.size))?;
-            worker.engines[black].uci_write_line("isready")?;
+            worker.engine(black).extend_cpu_limit();
+            worker
+                .engine(black)
+                .uci_write_line(&format!("teinewgame {}", self.size))?;
+            worker.engine(black).uci_write_line("isready")?;
         }
 
-        while worker.engines[white].uci_read_line()?.trim() != "readyok" {}
-        if self.white_engine_id != self.black_engine_id {
-            while worker.engines[black].uci_read_line()?.trim() != "readyok" {}
-        }
+        let white_ready = self.wait_for_readyok(worker.engine(white))?;
+        let black_ready = self.white_engine_id == self.black_engine_id
+            || self.wait_for_readyok(worker.engine(black))?;
 
-        let mut white_time = worker.engines[white].builder().game_time;
-        let mut black_time = worker.engines[black].builder().game_time;
+        let white_limits = worker.engine(white).builder().limits;
+        let black_limits = worker.engine(black).builder().limits;
 
-        let white_inc = worker.engines[white].builder().increment;
-        let black_inc = worker.engines[black].builder().increment;
+        // Engines without a time control have no clock, and never lose on time
+        let (mut white_time, white_inc) = white_limits.time_control.unwrap_or_default();
+        let (mut black_time, black_inc) = black_limits.time_control.unwrap_or_default();
 
-        let (result, result_description) = loop {
-            // TODO: Choose max game length
-            if moves.len() > 1000 {
+        let (result, termination) = loop {
+            // An engine that hangs before the game starts loses on time
+            if !white_ready || !black_ready {
+                let color = if white_ready {
+                    Color::Black
+                } else {
+                    Color::White
+                };
                 break (
-                    None,
-                    format!("Game terminated after reaching {} moves.", moves.len() / 2),
+                    Some(forfeit_win_str(!color)),
+                    Some(Termination::TimeForfeit(color)),
                 );
             }
-
             let result = position.pgn_game_result();
             if result.is_some() {
-                break (result, String::new());
+                break (result, None);
+            }
+            if let Some((result, termination)) = adjudicator.adjudicate() {
+                break (Some(result), Some(termination));
             }
-            let engine_to_move = match position.side_to_move() {
-                Color::White => &mut worker.engines[white],
-                Color::Black => &mut worker.engines[black],
+            let (engine_to_move, limits) = match position.side_to_move() {
+                Color::White => (worker.engine(white), white_limits),
+                Color::Black => (worker.engine(black), black_limits),
             };
 
             let start_time_for_move = Instant::now();
@@ -111,29 +277,63 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
                 position_board.do_move(mv.clone());
             }
 
-            let go_string = format!(
-                "go wtime {} btime {} winc {} binc {}",
-                white_time.as_millis(),
-                black_time.as_millis(),
-                white_inc.as_millis(),
-                black_inc.as_millis(),
-            );
+            // If the opponent has no clock, show it with the same clock as the engine to move
+            let (white_clock, black_clock) =
+                match (white_limits.time_control, black_limits.time_control) {
+                    (Some(_), None) => ((white_time, white_inc), (white_time, white_inc)),
+                    (None, Some(_)) => ((black_time, black_inc), (black_time, black_inc)),
+                    _ => ((white_time, white_inc), (black_time, black_inc)),
+                };
+            let go_string = limits.go_string(white_clock, black_clock);
+
+            // If the engine hasn't responded by the time its clock runs out plus a margin, assume it has hung
+            // Fixed-depth and fixed-nodes searches have no clock, and get a fixed timeout instead
+            let timeout = match (limits.time_control, limits.movetime) {
+                (Some(_), _) => {
+                    let time_left = match position.side_to_move() {
+                        Color::White => white_time,
+                        Color::Black => black_time,
+                    };
+                    time_left + engine_to_move.builder().timeout_margin
+                }
+                (None, Some(movetime)) => movetime + engine_to_move.builder().timeout_margin,
+                (None, None) => engine_to_move.builder().untimed_timeout,
+            };
 
-            let (move_string, last_uci_info) = match Self::play_move(
+            let (move_string, last_uci_info, last_exact_score) = match Self::play_move(
                 engine_to_move,
+                &position,
                 &position_string,
                 &go_string,
+                timeout,
             ) {
                 Ok(mv) => mv,
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    warn!("{} {} stopped responding during game {}. Game is counted as a loss on time, engine will be restarted.", engine_to_move.name(), thread::current().name().unwrap_or_default(), self.round_number);
+                    engine_to_move.restart()?;
+                    break (
+                        Some(forfeit_win_str(!position.side_to_move())),
+                        Some(Termination::TimeForfeit(position.side_to_move())),
+                    );
+                }
                 Err(err)
                     if err.kind() == io::ErrorKind::UnexpectedEof
                         || err.kind() == io::ErrorKind::BrokenPipe =>
                 {
-                    warn!("{} {} disconnected or crashed during game {}. Game is counted as a loss, engine will be restarted.", engine_to_move.name(), thread::current().name().unwrap_or_default(), self.round_number);
+                    let termination = match engine_to_move.exceeded_resource_limit() {
+                        Some(limit) => {
+                            warn!("{} {} {} during game {}. Game is counted as a loss, engine will be restarted.", engine_to_move.name(), thread::current().name().unwrap_or_default(), limit.exceeded_description(), self.round_number);
+                            Termination::ResourceLimit(position.side_to_move(), limit)
+                        }
+                        None => {
+                            warn!("{} {} disconnected or crashed during game {}. Game is counted as a loss, engine will be restarted.", engine_to_move.name(), thread::current().name().unwrap_or_default(), self.round_number);
+                            Termination::Disconnect(position.side_to_move())
+                        }
+                    };
                     engine_to_move.restart()?;
                     break (
                         Some(forfeit_win_str(!position.side_to_move())),
-                        format!("{} disconnected or crashed", position.side_to_move()),
+                        Some(termination),
                     );
                 }
                 Err(err) => {
@@ -151,7 +351,7 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             let Ok(mv) = position.move_from_lan(&move_string) else {
                 break (
                     Some(forfeit_win_str(!position.side_to_move())),
-                    format!("{} sent a malformed move", position.side_to_move()),
+                    Some(Termination::MalformedMove(position.side_to_move())),
                 );
             };
             let mut legal_moves = vec![];
@@ -160,47 +360,98 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             if !legal_moves.contains(&mv) {
                 break (
                     Some(forfeit_win_str(!position.side_to_move())),
-                    format!("{} made an illegal move", position.side_to_move()),
+                    Some(Termination::IllegalMove(position.side_to_move())),
                 );
             }
+            let pv_string = match &last_uci_info {
+                Some(uci_info) if annotation.pv => Some(Self::pv_string(&position, &uci_info.pv)),
+                _ => None,
+            };
             position.do_move(mv.clone());
 
-            let score_string = match last_uci_info {
-                Some(uci_info) => format!(
-                    "{:+.2}/{} {:.2}s",
-                    match position.side_to_move() {
-                        // Flip sign if last move was black's
-                        Color::White => uci_info.cp_score as f64 / -100.0,
-                        Color::Black => uci_info.cp_score as f64 / 100.0,
-                    },
+            // Bounds from aborted searches are not reliable enough for adjudication
+            adjudicator.add_position(
+                &position,
+                last_exact_score.map(|score| match position.side_to_move() {
+                    // Flip sign if last move was black's
+                    Color::White => -score,
+                    Color::Black => score,
+                }),
+            );
+
+            let time_forfeit = match !position.side_to_move() {
+                Color::White if white_limits.time_control.is_none() => false,
+                Color::Black if black_limits.time_control.is_none() => false,
+                Color::White if time_taken <= white_time => {
+                    white_time -= time_taken;
+                    white_time += white_inc;
+                    false
+                }
+                Color::Black if time_taken <= black_time => {
+                    black_time -= time_taken;
+                    black_time += black_inc;
+                    false
+                }
+                Color::White => {
+                    white_time = Duration::ZERO;
+                    true
+                }
+                Color::Black => {
+                    black_time = Duration::ZERO;
+                    true
+                }
+            };
+
+            let mut comment_parts = vec![];
+            // The time taken is written even if the engine sent no info, for the average move time
+            if last_uci_info.is_none() {
+                comment_parts.push(format!("{:.2}s", time_taken.as_secs_f32()));
+            }
+            if let Some(uci_info) = last_uci_info {
+                // Flip sign if last move was black's
+                let (score, bound) = match position.side_to_move() {
+                    Color::White => (-uci_info.score, -uci_info.bound),
+                    Color::Black => (uci_info.score, uci_info.bound),
+                };
+                comment_parts.push(format!(
+                    "{}{}/{} {:.2}s",
+                    score,
+                    bound,
                     uci_info.depth,
                     time_taken.as_secs_f32(),
-                ),
-                None => String::new(),
-            };
+                ));
+                if annotation.seldepth {
+                    comment_parts.push(format!("seldepth {}", uci_info.seldepth));
+                }
+                if annotation.nodes {
+                    comment_parts.push(format!("nodes {}", uci_info.nodes));
+                }
+                if annotation.nps {
+                    comment_parts.push(format!("nps {}", uci_info.nps));
+                }
+                if let Some(pv_string) = pv_string {
+                    comment_parts.push(format!("pv {}", pv_string));
+                }
+            }
+            if annotation.clock {
+                if white_limits.time_control.is_some() {
+                    comment_parts.push(format!("[%wclk {}]", format_clock(white_time)));
+                }
+                if black_limits.time_control.is_some() {
+                    comment_parts.push(format!("[%bclk {}]", format_clock(black_time)));
+                }
+            }
             moves.push(PtnMove {
                 mv,
                 annotations: vec![],
-                comment: score_string,
+                comment: comment_parts.join(" "),
             });
 
-            match !position.side_to_move() {
-                Color::White => {
-                    if time_taken <= white_time {
-                        white_time -= time_taken;
-                        white_time += white_inc;
-                    } else {
-                        break (Some("0-1"), "Black wins on time".to_string());
-                    }
-                }
-                Color::Black => {
-                    if time_taken <= black_time {
-                        black_time -= time_taken;
-                        black_time += black_inc;
-                    } else {
-                        break (Some("1-0"), "White wins on time".to_string());
-                    }
-                }
+            if time_forfeit {
+                break match !position.side_to_move() {
+                    Color::White => (Some("0-1"), Some(Termination::TimeForfeit(Color::White))),
+                    Color::Black => (Some("1-0"), Some(Termination::TimeForfeit(Color::Black))),
+                };
             }
         };
 
@@ -210,11 +461,11 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             ("Site".to_string(), "Racetrack".to_string()),
             (
                 "Player1".to_string(),
-                worker.engines[white].name().to_string(),
+                worker.engine(white).name().to_string(),
             ),
             (
                 "Player2".to_string(),
-                worker.engines[black].name().to_string(),
+                worker.engine(black).name().to_string(),
             ),
             ("Round".to_string(), (self.round_number + 1).to_string()),
             ("Size".to_string(), self.size.to_string()),
@@ -224,32 +475,17 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             ),
             (
                 "Clock".to_string(),
-                if worker.engines[white].builder().game_time
-                    == worker.engines[black].builder().game_time
-                    && white_inc == black_inc
-                {
-                    format!(
-                        "{}:{} +{:.1}",
-                        worker.engines[white].builder().game_time.as_secs() / 60,
-                        worker.engines[white].builder().game_time.as_secs() % 60,
-                        white_inc.as_secs_f32()
-                    )
+                if white_limits == black_limits {
+                    white_limits.to_string()
                 } else {
-                    format!(
-                        "{}:{} +{:.1} vs {}:{} +{:.1}",
-                        worker.engines[white].builder().game_time.as_secs() / 60,
-                        worker.engines[white].builder().game_time.as_secs() % 60,
-                        white_inc.as_secs_f32(),
-                        worker.engines[black].builder().game_time.as_secs() / 60,
-                        worker.engines[black].builder().game_time.as_secs() % 60,
-                        black_inc.as_secs_f32()
-                    )
+                    format!("{} vs {}", white_limits, black_limits)
                 },
             ),
         ];
 
         // Write Komi tag for non-zero komi
-        if let Some((_, komi_value_string)) = worker.engines[white]
+        if let Some((_, komi_value_string)) = worker
+            .engine(white)
             .builder()
             .desired_uci_options
             .iter()
@@ -268,8 +504,8 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
             }
         }
 
-        if !result_description.is_empty() {
-            tags.push(("Termination".to_string(), result_description));
+        if let Some(termination) = termination {
+            tags.push(("Termination".to_string(), termination.to_string()));
         }
 
         let game = Game {
@@ -281,23 +517,68 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
         Ok(game)
     }
 
+    /// Format the principal variation as PTN moves, starting from `position`
+    fn pv_string(position: &B, pv: &[B::Move]) -> String {
+        let mut position = position.clone();
+        let mut move_strings = vec![];
+        for mv in pv {
+            move_strings.push(position.move_to_san(mv));
+            position.do_move(mv.clone());
+        }
+        move_strings.join(" ")
+    }
+
+    /// Wait for the engine to answer `isready`. If it doesn't answer within its timeout margin,
+    /// it is assumed to have hung, and is restarted
+    fn wait_for_readyok(&self, engine: &mut Engine) -> io::Result<bool> {
+        let deadline = Instant::now() + engine.builder().timeout_margin;
+        loop {
+            match engine
+                .uci_read_line_with_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(input) if input.trim() == "readyok" => return Ok(true),
+                Ok(_) => (),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    warn!("{} {} did not respond to isready before game {}. Game is counted as a loss on time, engine will be restarted.", engine.name(), thread::current().name().unwrap_or_default(), self.round_number);
+                    engine.restart()?;
+                    return Ok(false);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Send the position to the engine and wait for its move.
+    /// Returns the move, the last info line for the engine's main line, and the last exact score in it
     fn play_move(
         engine_to_move: &mut Engine,
+        position: &B,
         position_string: &str,
         go_string: &str,
-    ) -> io::Result<(String, Option<UciInfo<B>>)> {
+        timeout: Duration,
+    ) -> io::Result<(String, Option<UciInfo<B>>, Option<Score>)> {
         engine_to_move.uci_write_line(position_string)?;
 
         engine_to_move.uci_write_line(go_string)?;
 
+        let deadline = Instant::now() + timeout;
         let mut last_uci_info: Option<UciInfo<B>> = None;
+        let mut last_exact_score = None;
 
         loop {
-            let input = engine_to_move.uci_read_line()?;
+            let input = engine_to_move
+                .uci_read_line_with_timeout(deadline.saturating_duration_since(Instant::now()))?;
 
             if input.starts_with("info") {
-                match parse_info_string(&input) {
-                    Ok(uci_info) => last_uci_info = Some(uci_info),
+                match parse_info_string(&input, position) {
+                    // With multipv, only keep the best line
+                    Ok(uci_info) if uci_info.multipv > 1 => (),
+                    Ok(uci_info) => {
+                        if uci_info.bound == ScoreBound::Exact {
+                            last_exact_score = Some(uci_info.score);
+                        }
+                        last_uci_info = Some(uci_info);
+                    }
                     Err(err) => warn!("Error in uci string \"{}\", ignoring. {}", input, err),
                 }
             }
@@ -309,8 +590,21 @@ impl<B: PgnPosition + Clone> ScheduledGame<B> {
                         .unwrap_or_default()
                         .to_string(),
                     last_uci_info,
+                    last_exact_score,
                 ));
             }
         }
     }
 }
+
+/// Format a clock as `h:mm:ss.s`, like the `[%clk]` comments in chess PGNs
+fn format_clock(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!(
+        "{}:{:02}:{:02}.{}",
+        tenths / 36000,
+        tenths / 600 % 60,
+        tenths / 10 % 60,
+        tenths % 10
+    )
+}
@@ -0,0 +1,289 @@
+use crate::game::ScheduledGame;
+use crate::openings::Opening;
+use crate::rating;
+use crate::simulation::MatchScore;
+use crate::tournament::EngineId;
+use board_game_traits::GameResult::*;
+use pgn_traits::PgnPosition;
+use std::str::FromStr;
+use tiltak::ptn::Game;
+
+/// How a tied knockout match is decided
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Play extra game pairs, one at a time, until one engine is ahead.
+    /// If the match is still tied after as many extra pairs as the match itself, the Elo rule applies
+    #[default]
+    ExtraPairs,
+    /// The engine with the higher rating, fitted to every game in the tournament so far, advances.
+    /// If the ratings are equal, the higher seed advances
+    Elo,
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "extra" => Ok(TieBreak::ExtraPairs),
+            "elo" => Ok(TieBreak::Elo),
+            _ => Err(format!(
+                "Invalid tie-break \"{}\", expected extra or elo",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BracketEntry {
+    Match(KnockoutMatch),
+    /// An engine that advances without playing, because the bracket isn't full
+    Bye(usize),
+}
+
+impl BracketEntry {
+    pub fn winner(&self) -> Option<usize> {
+        match self {
+            BracketEntry::Match(knockout_match) => knockout_match.winner,
+            BracketEntry::Bye(id) => Some(*id),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KnockoutMatch {
+    /// The higher-seeded engine first
+    pub engine_ids: [usize; 2],
+    /// The match's games, including tie-break pairs
+    pub game_ids: Vec<usize>,
+    pub extra_pairs: usize,
+    pub winner: Option<usize>,
+    /// Whether the match was tied, and decided by the engines' ratings
+    pub decided_by_elo: bool,
+}
+
+impl KnockoutMatch {
+    /// Each engine's score in the match's finished games, in half points
+    pub fn scores<B: PgnPosition>(
+        &self,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> [u64; 2] {
+        let mut scores = [0, 0];
+        for game_id in self.game_ids.iter() {
+            let Some(game) = finished_games.get(*game_id).and_then(Option::as_ref) else {
+                continue;
+            };
+            let scheduled_game = &scheduled_games[*game_id];
+            let first_engine_is_white = scheduled_game.white_engine_id.0 == self.engine_ids[0];
+            match (game.game_result(), first_engine_is_white) {
+                (Some(WhiteWin), true) | (Some(BlackWin), false) => scores[0] += 2,
+                (Some(WhiteWin), false) | (Some(BlackWin), true) => scores[1] += 2,
+                (None | Some(Draw), _) => {
+                    scores[0] += 1;
+                    scores[1] += 1;
+                }
+            }
+        }
+        scores
+    }
+}
+
+/// Single-elimination bracket. Engines are seeded in the order they were given, and the top seeds get byes
+/// if the number of engines is not a power of two. Each match is a number of game pairs,
+/// and a round is only scheduled once every match in the previous round has been decided
+pub struct Knockout<B: PgnPosition> {
+    num_engines: usize,
+    game_pairs: usize,
+    tie_break: TieBreak,
+    /// Whether the ratings for `TieBreak::Elo` account for white's first-move advantage
+    fit_white_advantage: bool,
+    openings: Vec<Opening<B>>,
+    openings_start_index: usize,
+    size: usize,
+    bracket: Vec<Vec<BracketEntry>>,
+}
+
+impl<B: PgnPosition + Clone> Knockout<B> {
+    pub fn new(
+        num_engines: usize,
+        game_pairs: usize,
+        tie_break: TieBreak,
+        fit_white_advantage: bool,
+        openings: Vec<Opening<B>>,
+        openings_start_index: usize,
+        size: usize,
+    ) -> Self {
+        Knockout {
+            num_engines,
+            game_pairs,
+            tie_break,
+            fit_white_advantage,
+            openings,
+            openings_start_index,
+            size,
+            bracket: vec![],
+        }
+    }
+
+    /// The rounds scheduled so far
+    pub fn bracket(&self) -> &[Vec<BracketEntry>] {
+        &self.bracket
+    }
+
+    pub fn champion(&self) -> Option<usize> {
+        match self.bracket.last() {
+            Some(round) if round.len() == 1 => round[0].winner(),
+            _ => None,
+        }
+    }
+
+    /// Decide the current round's matches, and schedule tie-break pairs or the next round.
+    /// All games in `scheduled_games` must be finished. Returns no games once a champion has been decided
+    pub fn next_round(
+        &mut self,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> Vec<ScheduledGame<B>> {
+        let mut games = vec![];
+
+        let pairings: Vec<(usize, Option<usize>)> = match self.bracket.last().cloned() {
+            None => {
+                let order = seeding_order(self.num_engines.next_power_of_two());
+                order
+                    .chunks(2)
+                    .map(|pair| (pair[0], Some(pair[1]).filter(|id| *id < self.num_engines)))
+                    .collect()
+            }
+            Some(mut round) => {
+                for entry in round.iter_mut() {
+                    let BracketEntry::Match(knockout_match) = entry else {
+                        continue;
+                    };
+                    if knockout_match.winner.is_some() {
+                        continue;
+                    }
+                    let [score1, score2] = knockout_match.scores(scheduled_games, finished_games);
+                    let [id1, id2] = knockout_match.engine_ids;
+                    if score1 != score2 {
+                        knockout_match.winner = Some(if score1 > score2 { id1 } else { id2 });
+                    } else if self.tie_break == TieBreak::ExtraPairs
+                        && knockout_match.extra_pairs < self.game_pairs
+                    {
+                        let pair = self.game_pair(id1, id2, scheduled_games.len() + games.len());
+                        knockout_match
+                            .game_ids
+                            .extend(pair.iter().map(|game| game.round_number));
+                        knockout_match.extra_pairs += 1;
+                        games.extend(pair);
+                    } else {
+                        knockout_match.winner =
+                            Some(self.elo_winner(id1, id2, scheduled_games, finished_games));
+                        knockout_match.decided_by_elo = true;
+                    }
+                }
+                let winners: Vec<usize> = round.iter().filter_map(BracketEntry::winner).collect();
+                let is_final = round.len() == 1;
+                *self.bracket.last_mut().unwrap() = round;
+                if !games.is_empty() || is_final {
+                    return games;
+                }
+                winners
+                    .chunks(2)
+                    .map(|pair| (pair[0].min(pair[1]), Some(pair[0].max(pair[1]))))
+                    .collect()
+            }
+        };
+
+        let mut round = vec![];
+        for (id1, id2) in pairings {
+            let Some(id2) = id2 else {
+                round.push(BracketEntry::Bye(id1));
+                continue;
+            };
+            let mut game_ids = vec![];
+            for _ in 0..self.game_pairs {
+                let pair = self.game_pair(id1, id2, scheduled_games.len() + games.len());
+                game_ids.extend(pair.iter().map(|game| game.round_number));
+                games.extend(pair);
+            }
+            round.push(BracketEntry::Match(KnockoutMatch {
+                engine_ids: [id1, id2],
+                game_ids,
+                extra_pairs: 0,
+                winner: None,
+                decided_by_elo: false,
+            }));
+        }
+        self.bracket.push(round);
+        games
+    }
+
+    /// Two games on the same opening, where the first engine starts with white
+    fn game_pair(&self, id1: usize, id2: usize, first_game_id: usize) -> Vec<ScheduledGame<B>> {
+        let opening =
+            &self.openings[(self.openings_start_index + first_game_id / 2) % self.openings.len()];
+        vec![
+            ScheduledGame {
+                round_number: first_game_id,
+                opening: opening.clone(),
+                white_engine_id: EngineId(id1),
+                black_engine_id: EngineId(id2),
+                size: self.size,
+            },
+            ScheduledGame {
+                round_number: first_game_id + 1,
+                opening: opening.clone(),
+                white_engine_id: EngineId(id2),
+                black_engine_id: EngineId(id1),
+                size: self.size,
+            },
+        ]
+    }
+
+    fn elo_winner(
+        &self,
+        id1: usize,
+        id2: usize,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> usize {
+        let mut color_results =
+            vec![vec![MatchScore::default(); self.num_engines]; self.num_engines];
+        for (scheduled_game, game) in scheduled_games
+            .iter()
+            .zip(finished_games.iter())
+            .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
+        {
+            let white_score = &mut color_results[scheduled_game.white_engine_id.0]
+                [scheduled_game.black_engine_id.0];
+            match game.game_result() {
+                Some(WhiteWin) => white_score.wins += 1,
+                Some(BlackWin) => white_score.losses += 1,
+                None | Some(Draw) => white_score.draws += 1,
+            }
+        }
+        let ratings = rating::fit_ratings(&color_results, self.fit_white_advantage);
+        // Treat ratings within half an elo as equal, so that rounding errors don't decide the match
+        if ratings.elos[id2] > ratings.elos[id1] + 0.5 {
+            id2
+        } else {
+            id1
+        }
+    }
+}
+
+/// Bracket positions of each seed, so that the top seeds meet as late as possible.
+/// Adjacent seeds in the returned order play each other in the first round
+pub(crate) fn seeding_order(bracket_size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < bracket_size {
+        let num_seeds = order.len() * 2;
+        order = order
+            .iter()
+            .flat_map(|seed| [*seed, num_seeds - 1 - *seed])
+            .collect();
+    }
+    order
+}
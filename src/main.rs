@@ -1,11 +1,12 @@
 use std::io::{BufWriter, Result};
 use std::sync::atomic::{self, AtomicBool};
-use std::{io, process, result};
+use std::{env, io, process, result};
 
-use crate::cli::CliOptions;
+use crate::cli::{CliCommand, CliError, CliOptions};
 use crate::engine::EngineBuilder;
+use crate::opening_report::OpeningReportSettings;
 use crate::pgn_writer::PgnWriter;
-use crate::tournament::{Tournament, TournamentSettings};
+use crate::tournament::{Tournament, TournamentSettings, TournamentType};
 use fern::InitError;
 use log::error;
 use openings::Opening;
@@ -14,20 +15,47 @@ use std::fs;
 use std::sync::Mutex;
 use tiltak::position::{Position, Settings};
 
+mod adjudication;
+mod affinity;
+mod check_engine;
 mod cli;
+mod config;
 mod engine;
 mod game;
+mod knockout;
+mod opening_report;
 mod openings;
 mod pgn_writer;
+mod progress;
+mod rating;
+mod resource_limits;
+mod results;
 mod simulation;
 mod sprt;
+mod spsa;
+mod stats;
+mod swiss;
 #[cfg(test)]
 mod tests;
 mod tournament;
 pub mod uci;
 
 fn main() -> Result<()> {
-    let cli_args = cli::parse_cli_arguments();
+    let command = match cli::parse_command_from(env::args_os()) {
+        Ok(command) => command,
+        Err(CliError::Clap(err)) => err.exit(),
+        Err(err @ CliError::Invalid(_)) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+    let cli_args = match command {
+        CliCommand::Tournament(cli_args) => cli_args,
+        CliCommand::CheckEngine(options) => {
+            let passed = check_engine::run(&options);
+            process::exit(if passed { 0 } else { 1 })
+        }
+    };
     println!("CLI args: {:?}", cli_args);
 
     match cli_args.size {
@@ -103,7 +131,7 @@ fn run_match<const S: usize>(
     cli_args: CliOptions,
     is_shutting_down: &'static AtomicBool,
 ) {
-    let engine_builders: Vec<EngineBuilder> = cli_args
+    let mut engine_builders: Vec<EngineBuilder> = cli_args
         .engines
         .iter()
         .map(|engine| {
@@ -114,14 +142,54 @@ fn run_match<const S: usize>(
             ));
             EngineBuilder {
                 path: engine.path.to_string(),
+                name: engine.name.clone(),
                 args: engine.cli_args.clone(),
+                working_dir: engine.working_dir.clone(),
+                env_vars: engine.env_vars.clone(),
                 desired_uci_options,
-                game_time: engine.time,
-                increment: engine.increment,
+                limits: engine.limits,
+                timeout_margin: cli_args.timeout_margin,
+                untimed_timeout: cli_args.untimed_timeout,
+                stderr: engine.stderr.clone(),
+                stderr_file_name: None,
+                worker_id: None,
+                cpu_core: None,
+                resource_limits: engine.resource_limits,
             }
         })
         .collect();
 
+    // SPSA tuning plays the engine against itself, with the options perturbed in opposite directions
+    if cli_args.tournament_type == TournamentType::Spsa {
+        let builder = engine_builders.pop().unwrap();
+        let name = builder.name.clone().unwrap_or_else(|| builder.path.clone());
+        engine_builders = vec![
+            EngineBuilder {
+                name: Some(format!("{}+", name)),
+                ..builder.clone()
+            },
+            EngineBuilder {
+                name: Some(format!("{}-", name)),
+                ..builder
+            },
+        ];
+    }
+
+    let resumed_games = match cli_args.pgnout.as_ref() {
+        Some(file_name) if cli_args.resume => match pgn_writer::games_from_file(file_name) {
+            Ok(games) => games,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                println!("{} not found, starting a new tournament", file_name);
+                vec![]
+            }
+            Err(err) => exit_with_error(&format!(
+                "Failed to read games from \"{}\" for resuming: {}",
+                file_name, err
+            )),
+        },
+        _ => vec![],
+    };
+
     let pgnout = if let Some(file_name) = cli_args.pgnout.as_ref() {
         PgnWriter::new(BufWriter::new(
             fs::OpenOptions::new()
@@ -146,6 +214,23 @@ fn run_match<const S: usize>(
         pgn_writer: Mutex::new(pgnout),
         tournament_type: cli_args.tournament_type,
         sprt: cli_args.sprt,
+        spsa: cli_args.spsa,
+        adjudication: cli_args.adjudication,
+        annotation: cli_args.annotation,
+        results_json: cli_args.results_json,
+        report_interval: cli_args.report_interval,
+        periodic_stats: cli_args.periodic_stats,
+        fit_white_advantage: cli_args.fit_white_advantage,
+        max_engines_per_worker: cli_args.max_engines_per_worker,
+        affinity: cli_args.affinity,
+        opening_report: (cli_args.opening_report || cli_args.filtered_book.is_some()).then(|| {
+            OpeningReportSettings {
+                print: cli_args.opening_report,
+                filtered_book: cli_args.filtered_book.clone(),
+                book_format: cli_args.book_format,
+            }
+        }),
+        resumed_games,
     };
 
     let tournament = Tournament::new(settings);
@@ -0,0 +1,159 @@
+use crate::game::ScheduledGame;
+use crate::openings::{BookFormat, Opening};
+use crate::simulation::MatchScore;
+use board_game_traits::GameResult::*;
+use pgn_traits::PgnPosition;
+use std::collections::HashMap;
+use tiltak::ptn::Game;
+
+/// What to do with the per-opening results at the end of the tournament
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpeningReportSettings {
+    /// Print each opening's results, and the lopsided openings
+    pub print: bool,
+    /// Write the book without the lopsided openings to this file, in the book's format
+    pub filtered_book: Option<String>,
+    pub book_format: BookFormat,
+}
+
+/// Results of the games played from one opening of the book
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpeningResult {
+    /// The opening's position in the book, starting at 0
+    pub index: usize,
+    /// The score from white's perspective
+    pub white_score: MatchScore,
+    /// Finished game pairs, where each engine played the opening once with each color
+    pub game_pairs: usize,
+    /// Game pairs where the same color won both games
+    pub lopsided_pairs: usize,
+}
+
+impl OpeningResult {
+    pub fn is_lopsided(&self) -> bool {
+        self.lopsided_pairs > 0
+    }
+}
+
+/// Each played opening's results, in book order
+pub fn opening_results<B: PgnPosition + Clone>(
+    openings: &[Opening<B>],
+    scheduled_games: &[ScheduledGame<B>],
+    finished_games: &[Option<Game<B>>],
+) -> Vec<OpeningResult> {
+    let indices: HashMap<String, usize> = openings
+        .iter()
+        .enumerate()
+        .map(|(index, opening)| (opening_name(opening), index))
+        .collect();
+
+    // Each opening's finished games, as white id, black id and result
+    let mut games_by_opening = vec![vec![]; openings.len()];
+    for (scheduled_game, game) in scheduled_games
+        .iter()
+        .zip(finished_games.iter())
+        .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
+    {
+        if let Some(index) = indices.get(&opening_name(&scheduled_game.opening)) {
+            games_by_opening[*index].push((
+                scheduled_game.white_engine_id.0,
+                scheduled_game.black_engine_id.0,
+                game.game_result().unwrap_or(Draw),
+            ));
+        }
+    }
+
+    games_by_opening
+        .into_iter()
+        .enumerate()
+        .filter(|(_, games)| !games.is_empty())
+        .map(|(index, games)| {
+            let mut result = OpeningResult {
+                index,
+                white_score: MatchScore::default(),
+                game_pairs: 0,
+                lopsided_pairs: 0,
+            };
+            for (_, _, game_result) in games.iter() {
+                match game_result {
+                    WhiteWin => result.white_score.wins += 1,
+                    BlackWin => result.white_score.losses += 1,
+                    Draw => result.white_score.draws += 1,
+                }
+            }
+            // A pair is two consecutive games on the opening with the colors reversed
+            let mut i = 0;
+            while i + 1 < games.len() {
+                let (white1, black1, result1) = games[i];
+                let (white2, black2, result2) = games[i + 1];
+                if (white1, black1) != (black2, white2) {
+                    i += 1;
+                    continue;
+                }
+                result.game_pairs += 1;
+                if result1 == result2 && result1 != Draw {
+                    result.lopsided_pairs += 1;
+                }
+                i += 2;
+            }
+            result
+        })
+        .collect()
+}
+
+/// The opening's moves, prefixed by its starting position if the opening doesn't start from the start position
+pub fn opening_name<B: PgnPosition + Clone>(opening: &Opening<B>) -> String {
+    if opening.root_position.to_fen() == B::start_position().to_fen() {
+        opening.moves_string()
+    } else if opening.moves.is_empty() {
+        opening.root_position.to_fen()
+    } else {
+        format!(
+            "{} {}",
+            opening.root_position.to_fen(),
+            opening.moves_string()
+        )
+    }
+}
+
+pub fn print_opening_results<B: PgnPosition + Clone>(
+    openings: &[Opening<B>],
+    results: &[OpeningResult],
+) {
+    println!("Opening results, from white's perspective:");
+    println!(
+        "{:>5} {:30} {:>14} {:>7} {:>6} {:>9}",
+        "#", "Opening", "Score", "", "Pairs", "Lopsided"
+    );
+    for result in results {
+        println!(
+            "{:>5} {:30} {:>14} {:>6.1}% {:>6} {:>9}",
+            result.index + 1,
+            opening_name(&openings[result.index]),
+            result.white_score.to_string(),
+            result.white_score.score() * 100.0,
+            result.game_pairs,
+            result.lopsided_pairs
+        );
+    }
+
+    let lopsided: Vec<&OpeningResult> = results.iter().filter(|r| r.is_lopsided()).collect();
+    if lopsided.is_empty() {
+        println!("No game pair was won twice by the same color");
+        return;
+    }
+    println!(
+        "{} of {} openings had a game pair won twice by the same color:",
+        lopsided.len(),
+        results.len()
+    );
+    for result in lopsided {
+        println!(
+            "{:>5} {}, {} of {} pairs",
+            result.index + 1,
+            opening_name(&openings[result.index]),
+            result.lopsided_pairs,
+            result.game_pairs
+        );
+    }
+}
@@ -3,8 +3,9 @@ use pgn_traits::PgnPosition;
 use std::fmt;
 use std::fs;
 use std::io;
-use std::io::BufRead;
-use tiltak::ptn::ptn_parser;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use tiltak::ptn::{ptn_parser, Game, PtnMove};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BookFormat {
@@ -13,6 +14,22 @@ pub enum BookFormat {
     MoveList,
 }
 
+impl FromStr for BookFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "move-list" => Ok(BookFormat::MoveList),
+            "tps" => Ok(BookFormat::Fen),
+            "ptn" => Ok(BookFormat::Pgn),
+            _ => Err(format!(
+                "Invalid book format \"{}\", expected move-list, tps or ptn",
+                input
+            )),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub struct Opening<B: PgnPosition> {
     pub root_position: B,
@@ -27,6 +44,19 @@ impl<B: PgnPosition + fmt::Debug> fmt::Debug for Opening<B> {
     }
 }
 
+impl<B: PgnPosition + Clone> Opening<B> {
+    /// The opening's moves in move notation, separated by spaces
+    pub fn moves_string(&self) -> String {
+        let mut position = self.root_position.clone();
+        let mut move_strings = vec![];
+        for mv in self.moves.iter() {
+            move_strings.push(position.move_to_san(mv));
+            position.do_move(mv.clone());
+        }
+        move_strings.join(" ")
+    }
+}
+
 pub fn openings_from_file<B: PgnPosition>(
     path: &str,
     format: BookFormat,
@@ -113,3 +143,36 @@ pub fn openings_from_ptn<B: PgnPosition, R: BufRead>(mut reader: R) -> io::Resul
         }),
     }
 }
+
+/// Write the openings to a file, in a format that can be read back as an opening book
+pub fn write_openings<B: PgnPosition + Clone>(
+    path: &str,
+    openings: &[Opening<B>],
+    format: BookFormat,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+    for opening in openings {
+        match format {
+            BookFormat::MoveList => writeln!(writer, "{}", opening.moves_string())?,
+            BookFormat::Fen => writeln!(writer, "{}", opening.root_position.to_fen())?,
+            BookFormat::Pgn => {
+                let game = Game {
+                    start_position: opening.root_position.clone(),
+                    moves: opening
+                        .moves
+                        .iter()
+                        .map(|mv| PtnMove {
+                            mv: mv.clone(),
+                            annotations: vec![],
+                            comment: String::new(),
+                        })
+                        .collect(),
+                    game_result_str: None,
+                    tags: vec![],
+                };
+                game.game_to_ptn(&mut writer)?;
+            }
+        }
+    }
+    writer.flush()
+}
@@ -1,8 +1,11 @@
 use board_game_traits::Position;
+use log::warn;
 use pgn_traits::PgnPosition;
+use std::collections::BTreeSet;
+use std::fs;
 use std::io;
-use std::io::Write;
-use tiltak::ptn::Game;
+use std::io::{Read, Write};
+use tiltak::ptn::{ptn_parser, Game};
 
 /// A wrapper around a `Write` instance, to ensure that PGNs are written in order
 ///
@@ -10,6 +13,8 @@ pub struct PgnWriter<B: Position> {
     pgn_out: Box<dyn io::Write + Send>,
     pending_games: Vec<(usize, Game<B>)>,
     next_game_number: usize,
+    // Games that are already in the output, from a previous run
+    skipped_games: BTreeSet<usize>,
 }
 
 impl<B: PgnPosition + Clone> PgnWriter<B> {
@@ -18,9 +23,17 @@ impl<B: PgnPosition + Clone> PgnWriter<B> {
             pgn_out: Box::new(pgn_out),
             pending_games: vec![],
             next_game_number: 0,
+            skipped_games: BTreeSet::new(),
         }
     }
 
+    /// Mark games as already written, so that they are not waited for.
+    /// Used when resuming a tournament, where some games were written by an earlier run
+    pub fn skip_games(&mut self, game_numbers: impl IntoIterator<Item = usize>) {
+        self.skipped_games.extend(game_numbers);
+        self.try_write_games().unwrap();
+    }
+
     pub fn submit_game(&mut self, game_number: usize, game: Game<B>) {
         self.pending_games.push((game_number, game));
         self.pending_games
@@ -29,12 +42,103 @@ impl<B: PgnPosition + Clone> PgnWriter<B> {
     }
 
     fn try_write_games(&mut self) -> io::Result<()> {
-        while !self.pending_games.is_empty() && self.pending_games[0].0 == self.next_game_number {
-            let game = self.pending_games.remove(0).1;
-            game.game_to_ptn(&mut self.pgn_out)?;
-            self.next_game_number += 1;
+        loop {
+            if self.skipped_games.remove(&self.next_game_number) {
+                self.next_game_number += 1;
+            } else if !self.pending_games.is_empty()
+                && self.pending_games[0].0 == self.next_game_number
+            {
+                let game = self.pending_games.remove(0).1;
+                game.game_to_ptn(&mut self.pgn_out)?;
+                self.next_game_number += 1;
+            } else {
+                break;
+            }
         }
         self.pgn_out.flush()?;
         Ok(())
     }
 }
+
+/// Read back all games from a ptn file written by an earlier run.
+/// If the run was killed while writing the last game, that game is moved from the file to `<path>.partial`,
+/// so that it is played again
+pub fn games_from_file<B: PgnPosition>(path: &str) -> io::Result<Vec<Game<B>>> {
+    let mut input = String::new();
+    fs::File::open(path)?.read_to_string(&mut input)?;
+    let (games, complete_length) = parse_games(&input)?;
+    if complete_length < input.len() {
+        let partial_game = &input[complete_length..];
+        let partial_path = format!("{}.partial", path);
+        // Keep a copy of the cut-off game, before removing it from the file
+        fs::write(&partial_path, partial_game)?;
+        let message = format!(
+            "The last game in {}, round {}, is incomplete. It was moved from byte offset {} to {}, and will be played again",
+            path,
+            round_tag(partial_game).unwrap_or("unknown"),
+            complete_length,
+            partial_path
+        );
+        println!("{}", message);
+        warn!("{}", message);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(complete_length as u64)?;
+    }
+    Ok(games)
+}
+
+/// The value of the `Round` tag in a game's ptn, if it has one
+fn round_tag(ptn: &str) -> Option<&str> {
+    ptn.lines()
+        .find_map(|line| line.trim_start().strip_prefix("[Round \""))
+        .and_then(|rest| rest.split('"').next())
+}
+
+/// Parse the games written by an earlier run, dropping the last game if it was cut off.
+/// Also returns the length of the input up to the end of the last complete game
+pub fn parse_games<B: PgnPosition>(input: &str) -> io::Result<(Vec<Game<B>>, usize)> {
+    match ptn_parser::parse_ptn(input) {
+        Ok(mut games) => {
+            // A game cut off in the middle of its moves may still parse, but has no result
+            if games
+                .last()
+                .is_some_and(|game| game.game_result_str.is_none())
+            {
+                games.pop();
+                Ok((games, last_game_start(input)))
+            } else {
+                Ok((games, input.len()))
+            }
+        }
+        Err(err) => {
+            // If the games before the last one can be read, only the last game was cut off
+            let complete_length = last_game_start(input);
+            let games = ptn_parser::parse_ptn(&input[..complete_length])
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, err))?;
+            Ok((games, complete_length))
+        }
+    }
+}
+
+/// Byte offset of the first tag of the last game
+fn last_game_start(input: &str) -> usize {
+    // Clock annotations in move comments also start with `[`, but tags start with the tag name
+    let is_tag = |line: &str| {
+        line.trim_start()
+            .strip_prefix('[')
+            .is_some_and(|tag| tag.starts_with(|c: char| c.is_ascii_alphabetic()))
+    };
+    let mut start = 0;
+    let mut offset = 0;
+    let mut previous_is_tag = false;
+    for line in input.split_inclusive('\n') {
+        if is_tag(line) && !previous_is_tag {
+            start = offset;
+        }
+        previous_is_tag = is_tag(line);
+        offset += line.len();
+    }
+    start
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// Timing of the games played in this run, for estimating how long the rest of the tournament will take
+pub struct Progress {
+    start_time: Instant,
+    concurrency: usize,
+    games_finished: usize,
+    total_game_time: Duration,
+}
+
+impl Progress {
+    pub fn new(concurrency: usize) -> Self {
+        Progress {
+            start_time: Instant::now(),
+            concurrency,
+            games_finished: 0,
+            total_game_time: Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of games finished in this run, including this one
+    pub fn record_game(&mut self, game_time: Duration) -> usize {
+        self.games_finished += 1;
+        self.total_game_time += game_time;
+        self.games_finished
+    }
+
+    pub fn games_per_minute(&self) -> f64 {
+        let minutes = self.start_time.elapsed().as_secs_f64() / 60.0;
+        if minutes > 0.0 {
+            self.games_finished as f64 / minutes
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimated time left, from the average game duration so far and the number of games played at once
+    pub fn eta(&self, remaining_games: usize) -> Option<Duration> {
+        if self.games_finished == 0 {
+            return None;
+        }
+        if remaining_games == 0 {
+            return Some(Duration::ZERO);
+        }
+        let average_game_time = self.total_game_time / self.games_finished as u32;
+        let parallel_games = self.concurrency.clamp(1, remaining_games);
+        Some(average_game_time * remaining_games as u32 / parallel_games as u32)
+    }
+}
+
+/// Format a duration as hours and minutes, minutes and seconds, or seconds
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,275 @@
+//! Maximum-likelihood Elo ratings for tournaments with more than two engines, in the style of BayesElo.
+//!
+//! Each game is modelled as `P(white wins) = f(Δ + advantage - draw_elo)`, `P(black wins) = f(-Δ - advantage - draw_elo)`,
+//! with the remaining probability being a draw, where `Δ` is the rating difference and `f` is the logistic Elo curve.
+
+use crate::simulation::MatchScore;
+
+/// Virtual draws added between every pair of engines that have played each other,
+/// to keep ratings finite when an engine has won or lost every game
+const PRIOR_DRAWS: f64 = 2.0;
+const MAX_ITERATIONS: usize = 1000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ratings {
+    /// Each engine's rating, relative to an average of 0
+    pub elos: Vec<f64>,
+    /// Covariance matrix of the ratings
+    pub covariance: Vec<Vec<f64>>,
+    pub white_advantage: f64,
+    pub draw_elo: f64,
+}
+
+impl Ratings {
+    /// Width of the 95% confidence interval on each side of the engine's rating
+    pub fn error_bar(&self, id: usize) -> f64 {
+        1.96 * self.covariance[id][id].max(0.0).sqrt()
+    }
+
+    /// Likelihood of superiority: The probability that engine `id1` is stronger than engine `id2`
+    pub fn los(&self, id1: usize, id2: usize) -> f64 {
+        let variance =
+            self.covariance[id1][id1] + self.covariance[id2][id2] - 2.0 * self.covariance[id1][id2];
+        let difference = self.elos[id1] - self.elos[id2];
+        if variance <= 0.0 {
+            return 0.5;
+        }
+        0.5 * (1.0 + erf(difference / (2.0 * variance).sqrt()))
+    }
+
+    /// Engine ids, sorted by rating from strongest to weakest
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = (0..self.elos.len()).collect();
+        ids.sort_by(|id1, id2| self.elos[*id2].total_cmp(&self.elos[*id1]));
+        ids
+    }
+}
+
+/// Fit ratings to the results of a tournament.
+/// `color_results[white][black]` is the score of engine `white` with the white pieces against engine `black`.
+/// Games an engine played against itself are ignored.
+/// If `fit_white_advantage` is false, white and black are assumed to be equally strong.
+pub fn fit_ratings(color_results: &[Vec<MatchScore>], fit_white_advantage: bool) -> Ratings {
+    let num_engines = color_results.len();
+
+    // Estimate the draw elo and white advantage from the overall results, as if all engines were equally strong
+    let (mut white_wins, mut draws, mut black_wins) = (0.0, 0.0, 0.0);
+    for (white, results) in color_results.iter().enumerate() {
+        for (black, score) in results.iter().enumerate() {
+            if white != black {
+                white_wins += score.wins as f64;
+                draws += score.draws as f64;
+                black_wins += score.losses as f64;
+            }
+        }
+    }
+    // Add one virtual game of each kind, to avoid infinite values
+    let num_games = white_wins + draws + black_wins + 3.0;
+    let (white_advantage, draw_elo) = if fit_white_advantage {
+        let x_white = inverse_logistic((white_wins + 1.0) / num_games);
+        let x_black = inverse_logistic((black_wins + 1.0) / num_games);
+        ((x_white - x_black) / 2.0, -(x_white + x_black) / 2.0)
+    } else {
+        let decisive_p = (white_wins + black_wins + 2.0) / num_games / 2.0;
+        (0.0, -inverse_logistic(decisive_p))
+    };
+
+    // Game counts for each ordered (white, black) pair, including the prior
+    let mut counts = vec![vec![(0.0, 0.0, 0.0); num_engines]; num_engines];
+    for white in 0..num_engines {
+        for black in 0..num_engines {
+            if white == black {
+                continue;
+            }
+            let score = color_results[white][black];
+            let reverse_score = color_results[black][white];
+            let (wins, draws, losses) = &mut counts[white][black];
+            *wins += score.wins as f64;
+            *draws += score.draws as f64;
+            *losses += score.losses as f64;
+            if score.num_games() + reverse_score.num_games() > 0 {
+                // Split evenly between the two colors
+                *draws += PRIOR_DRAWS / 2.0;
+            }
+        }
+    }
+
+    let mut elos = vec![0.0; num_engines];
+    let mut information = vec![vec![0.0; num_engines]; num_engines];
+
+    // Fisher scoring, i.e. Newton's method with the expected Hessian
+    for _ in 0..MAX_ITERATIONS {
+        let mut gradient = vec![0.0; num_engines];
+        information = vec![vec![0.0; num_engines]; num_engines];
+
+        for white in 0..num_engines {
+            for black in 0..num_engines {
+                let (wins, draws, losses) = counts[white][black];
+                let num_games = wins + draws + losses;
+                if white == black || num_games == 0.0 {
+                    continue;
+                }
+                let model = GameModel::new(elos[white] - elos[black], white_advantage, draw_elo);
+                let derivative = wins * model.white_win_derivative / model.white_win
+                    + losses * model.black_win_derivative / model.black_win
+                    + draws * model.draw_derivative / model.draw;
+                let game_information = num_games * model.information();
+
+                gradient[white] += derivative;
+                gradient[black] -= derivative;
+                information[white][white] += game_information;
+                information[black][black] += game_information;
+                information[white][black] -= game_information;
+                information[black][white] -= game_information;
+            }
+        }
+
+        let step = solve(&constrained(&information), &gradient);
+        for (elo, step) in elos.iter_mut().zip(step.iter()) {
+            // Limit the step size, in case the first steps are far from the optimum
+            *elo += step.clamp(-200.0, 200.0);
+        }
+        if step.iter().all(|step| step.abs() < 1e-6) {
+            break;
+        }
+    }
+
+    // The ratings are only determined up to a constant, so the information matrix is singular.
+    // Its pseudo-inverse gives the covariance of the ratings, when they are constrained to an average of 0
+    let inverse = invert(&constrained(&information));
+    let covariance = inverse
+        .iter()
+        .map(|row| row.iter().map(|x| x - 1.0 / num_engines as f64).collect())
+        .collect();
+
+    Ratings {
+        elos,
+        covariance,
+        white_advantage,
+        draw_elo,
+    }
+}
+
+/// Outcome probabilities of a single game, and their derivatives with respect to the rating difference
+struct GameModel {
+    white_win: f64,
+    black_win: f64,
+    draw: f64,
+    white_win_derivative: f64,
+    black_win_derivative: f64,
+    draw_derivative: f64,
+}
+
+impl GameModel {
+    fn new(rating_difference: f64, white_advantage: f64, draw_elo: f64) -> Self {
+        let white_win = logistic(rating_difference + white_advantage - draw_elo);
+        let black_win = logistic(-rating_difference - white_advantage - draw_elo);
+        let white_win_derivative = ELO_SCALE * white_win * (1.0 - white_win);
+        let black_win_derivative = -ELO_SCALE * black_win * (1.0 - black_win);
+        GameModel {
+            white_win,
+            black_win,
+            draw: 1.0 - white_win - black_win,
+            white_win_derivative,
+            black_win_derivative,
+            draw_derivative: -white_win_derivative - black_win_derivative,
+        }
+    }
+
+    /// Fisher information of a single game about the rating difference
+    fn information(&self) -> f64 {
+        self.white_win_derivative.powi(2) / self.white_win
+            + self.black_win_derivative.powi(2) / self.black_win
+            + self.draw_derivative.powi(2) / self.draw
+    }
+}
+
+const ELO_SCALE: f64 = std::f64::consts::LN_10 / 400.0;
+
+fn logistic(elo: f64) -> f64 {
+    1.0 / (1.0 + (-ELO_SCALE * elo).exp())
+}
+
+fn inverse_logistic(p: f64) -> f64 {
+    (p / (1.0 - p)).ln() / ELO_SCALE
+}
+
+/// Add `1/n` to every entry. This makes the information matrix invertible,
+/// and makes solutions sum to 0 when the right-hand side does.
+/// A tiny value is also added to the diagonal, in case some engines have not played each other at all
+fn constrained(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len() as f64;
+    matrix
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, x)| x + 1.0 / n + if i == j { 1e-12 } else { 0.0 })
+                .collect()
+        })
+        .collect()
+}
+
+/// Solve `matrix * x = rhs` with Gaussian elimination
+fn solve(matrix: &[Vec<f64>], rhs: &[f64]) -> Vec<f64> {
+    let n = matrix.len();
+    let mut augmented: Vec<Vec<f64>> = matrix
+        .iter()
+        .zip(rhs)
+        .map(|(row, value)| row.iter().copied().chain([*value]).collect())
+        .collect();
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|a, b| {
+                augmented[*a][column]
+                    .abs()
+                    .total_cmp(&augmented[*b][column].abs())
+            })
+            .unwrap();
+        augmented.swap(column, pivot);
+        let pivot_row = augmented[column].clone();
+        if pivot_row[column] == 0.0 {
+            continue;
+        }
+        for (row, values) in augmented.iter_mut().enumerate() {
+            if row != column {
+                let factor = values[column] / pivot_row[column];
+                for (value, pivot_value) in values.iter_mut().zip(&pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    (0..n)
+        .map(|row| augmented[row][n] / augmented[row][row])
+        .collect()
+}
+
+fn invert(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len();
+    let columns: Vec<Vec<f64>> = (0..n)
+        .map(|column| {
+            let unit: Vec<f64> = (0..n).map(|row| (row == column) as u8 as f64).collect();
+            solve(matrix, &unit)
+        })
+        .collect();
+    (0..n)
+        .map(|row| (0..n).map(|column| columns[column][row]).collect())
+        .collect()
+}
+
+/// Error function, with the approximation from Abramowitz and Stegun 7.1.26. Maximum error is 1.5e-7
+pub fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let result = 1.0 - polynomial * (-x * x).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        -result
+    }
+}
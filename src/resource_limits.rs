@@ -0,0 +1,178 @@
+use std::fmt;
+use std::io;
+use std::process::ExitStatus;
+
+/// Limits on an engine process, enforced by the operating system. Only supported on Linux
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Maximum virtual memory, in bytes
+    pub max_memory: Option<u64>,
+    /// Maximum cpu time in each game, in seconds
+    pub max_cpu_seconds: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_memory.is_none() && self.max_cpu_seconds.is_none()
+    }
+}
+
+/// A resource limit that an engine exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceLimit {
+    Memory,
+    CpuTime,
+}
+
+impl ResourceLimit {
+    /// What happened to an engine that exceeded this limit. Exceeding the memory limit can't be told apart from
+    /// other crashes, so that is only reported as the likely cause
+    pub fn exceeded_description(self) -> &'static str {
+        match self {
+            ResourceLimit::Memory => "crashed, likely from exceeding its memory limit",
+            ResourceLimit::CpuTime => "exceeded its cpu time limit",
+        }
+    }
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceLimit::Memory => write!(f, "memory"),
+            ResourceLimit::CpuTime => write!(f, "cpu time"),
+        }
+    }
+}
+
+/// Parse a memory size like `512M` or `4G`, in bytes. Suffixes are powers of 1024
+pub fn parse_memory_size(input: &str) -> Result<u64, String> {
+    let error = || format!("Couldn't parse memory size \"{}\"", input);
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&input[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&input[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&input[..i], 1 << 30),
+        Some((i, 't' | 'T')) => (&input[..i], 1 << 40),
+        _ => (input, 1),
+    };
+    let size: u64 = number.parse().map_err(|_| error())?;
+    match size.checked_mul(multiplier) {
+        Some(0) | None => Err(error()),
+        Some(bytes) => Ok(bytes),
+    }
+}
+
+/// Guess whether the process was stopped for exceeding one of its limits, from how it exited.
+/// Exceeding the cpu time limit sends `SIGXCPU`, while exceeding the memory limit makes allocations fail,
+/// which usually ends in an abort or a segfault. Other crashes end the same way, so `Memory` is only a likely cause
+#[cfg(target_os = "linux")]
+pub fn exceeded_limit(exit_status: ExitStatus, limits: &ResourceLimits) -> Option<ResourceLimit> {
+    use std::os::unix::process::ExitStatusExt;
+    match exit_status.signal()? {
+        libc::SIGXCPU if limits.max_cpu_seconds.is_some() => Some(ResourceLimit::CpuTime),
+        libc::SIGABRT | libc::SIGBUS | libc::SIGSEGV if limits.max_memory.is_some() => {
+            Some(ResourceLimit::Memory)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn exceeded_limit(_exit_status: ExitStatus, _limits: &ResourceLimits) -> Option<ResourceLimit> {
+    None
+}
+
+/// Set the limit on a process, or on the calling process if `pid` is 0.
+/// The hard limit is kept, so that the cpu time limit can be raised again for the next game
+#[cfg(target_os = "linux")]
+fn set_limit(pid: u32, resource: ResourceLimit, limit: u64) -> io::Result<()> {
+    use std::convert::TryFrom;
+    let resource = match resource {
+        ResourceLimit::Memory => libc::RLIMIT_AS,
+        ResourceLimit::CpuTime => libc::RLIMIT_CPU,
+    };
+    let mut old_limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // Safety: The limits are valid for reads and writes
+    unsafe {
+        if libc::prlimit(
+            pid as libc::pid_t,
+            resource,
+            std::ptr::null(),
+            &mut old_limit,
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        // `rlim_t` is only 32 bits on 32-bit Linux, where larger limits are the same as no limit
+        let new_limit = libc::rlimit {
+            rlim_cur: libc::rlim_t::try_from(limit)
+                .unwrap_or(libc::RLIM_INFINITY)
+                .min(old_limit.rlim_max),
+            rlim_max: old_limit.rlim_max,
+        };
+        if libc::prlimit(
+            pid as libc::pid_t,
+            resource,
+            &new_limit,
+            std::ptr::null_mut(),
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Apply the limits to the calling process. Used in a newly started engine process, before it runs the engine
+#[cfg(target_os = "linux")]
+pub fn apply(limits: &ResourceLimits) -> io::Result<()> {
+    if let Some(max_memory) = limits.max_memory {
+        set_limit(0, ResourceLimit::Memory, max_memory)?;
+    }
+    if let Some(max_cpu_seconds) = limits.max_cpu_seconds {
+        set_limit(0, ResourceLimit::CpuTime, max_cpu_seconds)?;
+    }
+    Ok(())
+}
+
+/// The cpu time limit covers the whole life of the process, so before each game,
+/// raise it to the cpu time used so far plus the limit
+#[cfg(target_os = "linux")]
+pub fn extend_cpu_limit(pid: u32, max_cpu_seconds: u64) -> io::Result<()> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid))?;
+    // The process name is in parentheses, and may contain spaces
+    let fields: Vec<&str> = stat
+        .rsplit_once(')')
+        .map(|(_, rest)| rest.split_whitespace().collect())
+        .unwrap_or_default();
+    // utime and stime are the 14th and 15th fields, counting the pid and the name
+    let parse_ticks = |index: usize| -> io::Result<u64> {
+        fields
+            .get(index - 3)
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed /proc stat"))
+    };
+    let ticks = parse_ticks(14)? + parse_ticks(15)?;
+    // Safety: sysconf has no preconditions
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+    let used_seconds = ticks.div_ceil(ticks_per_second);
+    set_limit(pid, ResourceLimit::CpuTime, used_seconds + max_cpu_seconds)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_limits: &ResourceLimits) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Resource limits are only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn extend_cpu_limit(_pid: u32, _max_cpu_seconds: u64) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Resource limits are only supported on Linux",
+    ))
+}
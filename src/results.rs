@@ -0,0 +1,108 @@
+use crate::simulation::{self, MatchScore, ScoreEstimate};
+use crate::sprt::PentanomialResult;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+/// Machine-readable results of a tournament, written with `--results-json`
+#[derive(Clone, Debug, Serialize)]
+pub struct ResultsReport {
+    pub engines: Vec<EngineReport>,
+    pub games_played: usize,
+    pub white_wins: u64,
+    pub black_wins: u64,
+    pub draws: u64,
+    /// Every scheduled game, including those that have not been played yet
+    pub games: Vec<GameReport>,
+    pub pairs: Vec<PairReport>,
+    /// One test for each SPRT candidate
+    pub sprt: Vec<SprtReport>,
+}
+
+/// An engine's total results against all other engines
+#[derive(Clone, Debug, Serialize)]
+pub struct EngineReport {
+    pub name: String,
+    pub score: MatchScore,
+    /// Only calculated for formats with more than two engines
+    pub rating: Option<RatingReport>,
+}
+
+/// Maximum-likelihood rating, relative to an average of 0, with a 95% confidence interval
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct RatingReport {
+    pub elo: f64,
+    pub error: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GameReport {
+    pub round: usize,
+    pub white: String,
+    pub black: String,
+    pub opening_tps: String,
+    pub opening_moves: String,
+    /// `None` if the game has not been played yet
+    pub result: Option<String>,
+    pub termination: Option<String>,
+}
+
+/// Head-to-head results between two engines, from the first engine's perspective
+#[derive(Clone, Debug, Serialize)]
+pub struct PairReport {
+    pub engine: String,
+    pub opponent: String,
+    pub score: MatchScore,
+    pub pentanomial: PentanomialResult,
+    pub elo: EloReport,
+}
+
+/// Elo difference with a 95% confidence interval. Infinite or undefined values are written as `null`
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct EloReport {
+    pub lower: Option<i32>,
+    pub expected: Option<i32>,
+    pub upper: Option<i32>,
+}
+
+impl From<ScoreEstimate> for EloReport {
+    fn from(estimate: ScoreEstimate) -> Self {
+        EloReport {
+            lower: simulation::to_elo(estimate.lower),
+            expected: simulation::to_elo(estimate.expected),
+            upper: simulation::to_elo(estimate.upper),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SprtReport {
+    pub base: String,
+    pub under_test: String,
+    pub elo0: f64,
+    pub elo1: f64,
+    pub llr: f64,
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    pub status: SprtStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SprtStatus {
+    Running,
+    Passed,
+    Failed,
+}
+
+/// Write the report to a temporary file first, and then move it into place,
+/// so that readers never see a partially written file
+pub fn write_json(path: &str, report: &ResultsReport) -> io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = fs::File::create(&tmp_path)?;
+    serde_json::to_writer_pretty(&mut file, report)?;
+    writeln!(file)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
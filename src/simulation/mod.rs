@@ -3,8 +3,9 @@ use std::{cmp::Ordering, fmt::Display};
 use bpci::{Interval, NSuccessesSample, WilsonScore};
 use rand::{Rng, SeedableRng};
 use rand_distr::{Distribution, Normal};
+use serde::Serialize;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize)]
 pub struct MatchScore {
     pub wins: u64,
     pub draws: u64,
@@ -99,6 +100,56 @@ impl FullWinstonSimulation {
     }
 }
 
+/// Expected score, with a 95% confidence interval
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScoreEstimate {
+    pub lower: f32,
+    pub expected: f32,
+    pub upper: f32,
+}
+
+impl ScoreEstimate {
+    pub fn new(score: MatchScore) -> Self {
+        if score.num_games() == 0 {
+            return ScoreEstimate {
+                lower: f32::NAN,
+                expected: f32::NAN,
+                upper: f32::NAN,
+            };
+        }
+        let full_simulation = FullWinstonSimulation::run_simulation(score);
+        ScoreEstimate {
+            lower: full_simulation.result_for_p(0.025),
+            expected: score.score(),
+            upper: full_simulation.result_for_p(0.975),
+        }
+    }
+
+    /// Confidence interval from the normal approximation of the score's distribution.
+    /// Much faster than `new`, but less accurate after few games, or with lopsided scores
+    pub fn normal_approximation(score: MatchScore) -> Self {
+        if score.num_games() == 0 {
+            return ScoreEstimate {
+                lower: f32::NAN,
+                expected: f32::NAN,
+                upper: f32::NAN,
+            };
+        }
+        let num_games = score.num_games() as f64;
+        let expected = score.score() as f64;
+        let variance = (score.wins as f64 * (1.0 - expected).powi(2)
+            + score.draws as f64 * (0.5 - expected).powi(2)
+            + score.losses as f64 * expected.powi(2))
+            / num_games;
+        let margin = 1.96 * (variance / num_games).sqrt();
+        ScoreEstimate {
+            lower: (expected - margin).max(0.0) as f32,
+            expected: expected as f32,
+            upper: (expected + margin).min(1.0) as f32,
+        }
+    }
+}
+
 pub fn to_elo(p: f32) -> Option<i32> {
     let elo = -400.0 * ((1.0 - p) / p).log10();
     if elo.is_finite() {
@@ -1,8 +1,9 @@
+use serde::Serialize;
 use std::convert::TryInto;
 
 // This is an implementation of GSPRT under a pentanomial model.
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize)]
 pub struct PentanomialResult {
     pub ww: usize,
     pub wd: usize,
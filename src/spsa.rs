@@ -0,0 +1,249 @@
+use crate::engine::Engine;
+use crate::uci::UciOptionType;
+use board_game_traits::GameResult;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+/// Settings for tuning engine options with SPSA (Simultaneous Perturbation Stochastic Approximation).
+/// Uses the same parametrization as Fishtest, where each parameter's step size is given for the end of the tuning run
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpsaSettings {
+    /// Names of the tuned options, and how much each option is perturbed at the end of the run
+    pub parameters: Vec<(String, f64)>,
+    /// Learning rate at the end of the run, relative to the square of the perturbation
+    pub learning_rate: f64,
+    /// Stability constant `A`. Defaults to 10% of the number of iterations
+    pub stability: Option<f64>,
+    pub alpha: f64,
+    pub gamma: f64,
+    /// CSV file to write the parameter values to, after each iteration
+    pub log_path: String,
+}
+
+impl SpsaSettings {
+    pub fn new(parameters: Vec<(String, f64)>) -> Self {
+        SpsaSettings {
+            parameters,
+            learning_rate: 0.002,
+            stability: None,
+            alpha: 0.602,
+            gamma: 0.101,
+            log_path: "spsa.csv".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpsaParameter {
+    pub name: String,
+    pub value: f64,
+    pub min: i64,
+    pub max: i64,
+    /// Perturbation at the end of the run
+    c_end: f64,
+}
+
+/// The parameter values for one game pair, where the engine with `plus` values plays the engine with `minus` values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Perturbation {
+    /// For each parameter, whether it was perturbed up or down, as +1.0 or -1.0
+    directions: Vec<f64>,
+    pub plus: Vec<(String, i64)>,
+    pub minus: Vec<(String, i64)>,
+    /// The plus engine's score in the pair's finished games, as wins minus losses
+    plus_score: i64,
+    games_finished: usize,
+}
+
+/// Tuning state, shared by all the worker threads. Each game pair is one SPSA iteration
+pub struct Spsa {
+    settings: SpsaSettings,
+    parameters: Vec<SpsaParameter>,
+    num_iterations: usize,
+    iterations_finished: usize,
+    perturbations: HashMap<usize, Perturbation>,
+    log: Option<Box<dyn io::Write + Send>>,
+}
+
+impl Spsa {
+    pub fn new(settings: SpsaSettings, num_iterations: usize) -> Self {
+        Spsa {
+            settings,
+            parameters: vec![],
+            num_iterations,
+            iterations_finished: 0,
+            perturbations: HashMap::new(),
+            log: None,
+        }
+    }
+
+    /// Read the tuned options' starting values and bounds from the engine, and open the log file
+    pub fn init(&mut self, engine: &Engine) -> Result<(), String> {
+        for (name, _) in self.settings.parameters.clone() {
+            match engine.option(&name).map(|option| &option.option_type) {
+                Some(UciOptionType::Spin(value, min, max)) => {
+                    self.add_parameter(&name, *value, *min, *max)
+                }
+                Some(_) => {
+                    return Err(format!(
+                        "Option {} of engine {} is not a spin option, and cannot be tuned",
+                        name,
+                        engine.name()
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "Engine {} does not have the option {}",
+                        engine.name(),
+                        name
+                    ))
+                }
+            }
+        }
+        let mut log = fs::File::create(&self.settings.log_path).map_err(|err| {
+            format!(
+                "Failed to create spsa log \"{}\": {}",
+                self.settings.log_path, err
+            )
+        })?;
+        let header: Vec<&str> = self.parameters.iter().map(|p| p.name.as_str()).collect();
+        writeln!(log, "iteration,{}", header.join(",")).map_err(|err| err.to_string())?;
+        self.log = Some(Box::new(log));
+        self.write_log_line().map_err(|err| err.to_string())
+    }
+
+    pub fn add_parameter(&mut self, name: &str, value: i64, min: i64, max: i64) {
+        let c_end = self
+            .settings
+            .parameters
+            .iter()
+            .find(|(parameter_name, _)| parameter_name == name)
+            .map(|(_, c_end)| *c_end)
+            .unwrap();
+        self.parameters.push(SpsaParameter {
+            name: name.to_string(),
+            value: value as f64,
+            min,
+            max,
+            c_end,
+        })
+    }
+
+    pub fn parameters(&self) -> &[SpsaParameter] {
+        &self.parameters
+    }
+
+    pub fn num_iterations(&self) -> usize {
+        self.num_iterations
+    }
+
+    pub fn iterations_finished(&self) -> usize {
+        self.iterations_finished
+    }
+
+    fn stability(&self) -> f64 {
+        self.settings
+            .stability
+            .unwrap_or(0.1 * self.num_iterations as f64)
+    }
+
+    /// Perturbation size for the given iteration
+    fn c_k(&self, parameter: &SpsaParameter, iteration: usize) -> f64 {
+        let c = parameter.c_end * (self.num_iterations as f64).powf(self.settings.gamma);
+        c / (iteration as f64 + 1.0).powf(self.settings.gamma)
+    }
+
+    /// Step size for the given iteration
+    fn a_k(&self, parameter: &SpsaParameter, iteration: usize) -> f64 {
+        let a_end = self.settings.learning_rate * parameter.c_end.powi(2);
+        let a = a_end * (self.stability() + self.num_iterations as f64).powf(self.settings.alpha);
+        a / (self.stability() + iteration as f64 + 1.0).powf(self.settings.alpha)
+    }
+
+    /// The option values to play a game pair with. Both games in the pair use the same values
+    pub fn perturbation(&mut self, pair_number: usize) -> Perturbation {
+        if let Some(perturbation) = self.perturbations.get(&pair_number) {
+            return perturbation.clone();
+        }
+        let directions: Vec<f64> = self
+            .parameters
+            .iter()
+            .map(|_| if rand::random() { 1.0 } else { -1.0 })
+            .collect();
+        let values = |sign: f64| -> Vec<(String, i64)> {
+            self.parameters
+                .iter()
+                .zip(directions.iter())
+                .map(|(parameter, direction)| {
+                    let value =
+                        parameter.value + sign * direction * self.c_k(parameter, pair_number);
+                    (
+                        parameter.name.clone(),
+                        (value.round() as i64).clamp(parameter.min, parameter.max),
+                    )
+                })
+                .collect()
+        };
+        let perturbation = Perturbation {
+            plus: values(1.0),
+            minus: values(-1.0),
+            directions,
+            plus_score: 0,
+            games_finished: 0,
+        };
+        self.perturbations.insert(pair_number, perturbation.clone());
+        perturbation
+    }
+
+    /// Record a finished game. Once both games in a pair are finished, the parameters are updated
+    pub fn record_game(
+        &mut self,
+        pair_number: usize,
+        plus_is_white: bool,
+        result: GameResult,
+    ) -> io::Result<()> {
+        let perturbation = self.perturbations.get_mut(&pair_number).unwrap();
+        perturbation.plus_score += match (result, plus_is_white) {
+            (GameResult::WhiteWin, true) | (GameResult::BlackWin, false) => 1,
+            (GameResult::Draw, _) => 0,
+            (GameResult::WhiteWin, false) | (GameResult::BlackWin, true) => -1,
+        };
+        perturbation.games_finished += 1;
+        if perturbation.games_finished < 2 {
+            return Ok(());
+        }
+
+        let perturbation = self.perturbations.remove(&pair_number).unwrap();
+        let steps: Vec<f64> = self
+            .parameters
+            .iter()
+            .zip(perturbation.directions.iter())
+            .map(|(parameter, direction)| {
+                self.a_k(parameter, pair_number) / self.c_k(parameter, pair_number)
+                    * perturbation.plus_score as f64
+                    * direction
+            })
+            .collect();
+        for (parameter, step) in self.parameters.iter_mut().zip(steps) {
+            parameter.value =
+                (parameter.value + step).clamp(parameter.min as f64, parameter.max as f64);
+        }
+        self.iterations_finished += 1;
+        self.write_log_line()
+    }
+
+    fn write_log_line(&mut self) -> io::Result<()> {
+        if let Some(log) = self.log.as_mut() {
+            let values: Vec<String> = self
+                .parameters
+                .iter()
+                .map(|parameter| format!("{:.3}", parameter.value))
+                .collect();
+            writeln!(log, "{},{}", self.iterations_finished, values.join(","))?;
+            log.flush()?;
+        }
+        Ok(())
+    }
+}
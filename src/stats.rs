@@ -0,0 +1,233 @@
+use crate::game::{ScheduledGame, Termination};
+use crate::simulation::MatchScore;
+use board_game_traits::Color;
+use board_game_traits::GameResult::*;
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+use std::time::Duration;
+use tiltak::ptn::Game;
+
+/// Width of each bar in the game length histogram, in plies
+const HISTOGRAM_BUCKET_SIZE: usize = 10;
+const HISTOGRAM_MAX_WIDTH: u64 = 40;
+
+/// Terminations where the losing engine, rather than the winner, is credited with the game
+const FORFEIT_KINDS: [&str; 5] = ["time", "crash", "limit", "malformed", "illegal"];
+
+/// How the game ended, as a short name. Games that ended normally are named after the game result
+pub fn termination_kind<B: PgnPosition>(game: &Game<B>) -> &'static str {
+    if let Some((_, value)) = game.tags.iter().find(|(tag, _)| tag == "Termination") {
+        return value
+            .parse::<Termination>()
+            .map_or("other", Termination::kind);
+    }
+    match game.game_result_str {
+        Some(result) if result.contains('R') => "road",
+        Some(result) if result.contains('F') => "flat",
+        Some("1-0") | Some("0-1") => "win",
+        _ => "draw",
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EngineStats {
+    /// Number of games ending each way. Wins are counted for the winner, forfeits for the engine that forfeited,
+    /// and draws for both engines
+    pub terminations: Vec<(&'static str, u64)>,
+    pub white_score: MatchScore,
+    pub black_score: MatchScore,
+    /// Total thinking time, for the moves where the time was recorded
+    pub move_time: Duration,
+    pub timed_moves: u32,
+}
+
+impl EngineStats {
+    pub fn average_move_time(&self) -> Option<Duration> {
+        if self.timed_moves == 0 {
+            None
+        } else {
+            Some(self.move_time / self.timed_moves)
+        }
+    }
+
+    fn add_termination(&mut self, kind: &'static str) {
+        match self
+            .terminations
+            .iter_mut()
+            .find(|(other, _)| *other == kind)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.terminations.push((kind, 1)),
+        }
+    }
+}
+
+/// Statistics on how the finished games ended, beyond their results
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameStats {
+    /// Number of games in each bucket of `HISTOGRAM_BUCKET_SIZE` plies, including the opening moves
+    pub length_histogram: Vec<u64>,
+    pub engines: Vec<EngineStats>,
+}
+
+impl GameStats {
+    pub fn new<B: PgnPosition>(
+        num_engines: usize,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> Self {
+        let mut stats = GameStats {
+            length_histogram: vec![],
+            engines: vec![EngineStats::default(); num_engines],
+        };
+        for (scheduled_game, game) in scheduled_games
+            .iter()
+            .zip(finished_games.iter())
+            .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
+        {
+            let bucket = game.moves.len() / HISTOGRAM_BUCKET_SIZE;
+            if stats.length_histogram.len() <= bucket {
+                stats.length_histogram.resize(bucket + 1, 0);
+            }
+            stats.length_histogram[bucket] += 1;
+
+            let white = scheduled_game.white_engine_id.0;
+            let black = scheduled_game.black_engine_id.0;
+            let kind = termination_kind(game);
+            match game.game_result() {
+                Some(WhiteWin) => {
+                    stats.engines[white].white_score.wins += 1;
+                    stats.engines[black].black_score.losses += 1;
+                    let credited = if FORFEIT_KINDS.contains(&kind) {
+                        black
+                    } else {
+                        white
+                    };
+                    stats.engines[credited].add_termination(kind);
+                }
+                Some(BlackWin) => {
+                    stats.engines[white].white_score.losses += 1;
+                    stats.engines[black].black_score.wins += 1;
+                    let credited = if FORFEIT_KINDS.contains(&kind) {
+                        white
+                    } else {
+                        black
+                    };
+                    stats.engines[credited].add_termination(kind);
+                }
+                None | Some(Draw) => {
+                    stats.engines[white].white_score.draws += 1;
+                    stats.engines[black].black_score.draws += 1;
+                    stats.engines[white].add_termination(kind);
+                    // With the book test format, an engine can play itself
+                    if black != white {
+                        stats.engines[black].add_termination(kind);
+                    }
+                }
+            }
+
+            // The opening moves have no time recorded
+            let mut side_to_move = game.start_position.side_to_move();
+            for (i, ptn_move) in game.moves.iter().enumerate() {
+                let engine_id = match side_to_move {
+                    Color::White => white,
+                    Color::Black => black,
+                };
+                if i >= scheduled_game.opening.moves.len() {
+                    if let Some(move_time) = move_time(&ptn_move.comment) {
+                        stats.engines[engine_id].move_time += move_time;
+                        stats.engines[engine_id].timed_moves += 1;
+                    }
+                }
+                side_to_move = !side_to_move;
+            }
+        }
+        stats
+    }
+
+    pub fn print(&self, engine_names: &[String]) {
+        println!("Game length in plies:");
+        let max_count = self
+            .length_histogram
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_default();
+        for (bucket, count) in self.length_histogram.iter().enumerate() {
+            let bar_width = (count * HISTOGRAM_MAX_WIDTH + max_count - 1) / max_count.max(1);
+            println!(
+                "{:>4}-{:<4} {:>6} {}",
+                bucket * HISTOGRAM_BUCKET_SIZE,
+                (bucket + 1) * HISTOGRAM_BUCKET_SIZE - 1,
+                count,
+                "#".repeat(bar_width as usize)
+            );
+        }
+
+        let mut kinds: Vec<&str> = vec![];
+        for (kind, _) in self
+            .engines
+            .iter()
+            .flat_map(|engine| engine.terminations.iter())
+        {
+            if !kinds.contains(kind) {
+                kinds.push(*kind);
+            }
+        }
+        println!("Terminations:");
+        print!("{:16}", "Name");
+        for kind in kinds.iter() {
+            print!(" {:>10}", kind);
+        }
+        println!();
+        for (engine, name) in self.engines.iter().zip(engine_names) {
+            print!("{:16}", name);
+            for kind in kinds.iter() {
+                let count = engine
+                    .terminations
+                    .iter()
+                    .find(|(other, _)| other == kind)
+                    .map_or(0, |(_, count)| *count);
+                print!(" {:>10}", count);
+            }
+            println!();
+        }
+
+        println!(
+            "{:16} {:>20} {:>20} {:>10}",
+            "Name", "As white", "As black", "Move time"
+        );
+        for (engine, name) in self.engines.iter().zip(engine_names) {
+            println!(
+                "{:16} {:>20} {:>20} {:>10}",
+                name,
+                score_string(engine.white_score),
+                score_string(engine.black_score),
+                engine
+                    .average_move_time()
+                    .map(|time| format!("{:.2}s", time.as_secs_f32()))
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn score_string(score: MatchScore) -> String {
+    if score.num_games() == 0 {
+        return String::new();
+    }
+    format!("{} {:.1}%", score, score.score() * 100.0)
+}
+
+/// Read the time taken from a move comment, which starts with `score/depth time`,
+/// or only with the time if the engine didn't send a score
+pub(crate) fn move_time(comment: &str) -> Option<Duration> {
+    let mut words = comment.split_whitespace();
+    let mut time = words.next()?;
+    if time.contains('/') {
+        time = words.next()?;
+    }
+    let seconds: f64 = time.strip_suffix('s')?.parse().ok()?;
+    // Negative, infinite or NaN times in a hand-edited file are ignored
+    Duration::try_from_secs_f64(seconds).ok()
+}
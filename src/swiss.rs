@@ -0,0 +1,198 @@
+use crate::game::ScheduledGame;
+use crate::openings::Opening;
+use crate::tournament::EngineId;
+use board_game_traits::GameResult::*;
+use pgn_traits::PgnPosition;
+use std::cmp::Reverse;
+use tiltak::ptn::Game;
+
+/// Maximum number of pairings to try before allowing rematches
+const PAIRING_SEARCH_LIMIT: usize = 100_000;
+
+/// Pairing for a Swiss-system tournament. Rounds are generated one at a time, once the previous round has finished.
+/// Each round, engines are paired with an opponent with a similar score that they haven't played yet,
+/// and each pairing plays a game pair on a shared opening. With an odd number of engines,
+/// the lowest-ranked engine without a bye sits out the round, and is scored as if it won both games.
+pub struct Swiss<B: PgnPosition> {
+    num_engines: usize,
+    rounds: usize,
+    openings: Vec<Opening<B>>,
+    openings_start_index: usize,
+    size: usize,
+}
+
+impl<B: PgnPosition + Clone> Swiss<B> {
+    pub fn new(
+        num_engines: usize,
+        rounds: usize,
+        openings: Vec<Opening<B>>,
+        openings_start_index: usize,
+        size: usize,
+    ) -> Self {
+        Swiss {
+            num_engines,
+            rounds,
+            openings,
+            openings_start_index,
+            size,
+        }
+    }
+
+    /// Schedule the next round. All games in `scheduled_games` must be finished.
+    /// Returns no games once every round has been scheduled
+    pub fn next_round(
+        &self,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> Vec<ScheduledGame<B>> {
+        if scheduled_games.len() >= self.rounds * games_per_round(self.num_engines) {
+            return vec![];
+        }
+        let scores = scores(self.num_engines, scheduled_games, finished_games);
+        let byes = byes(self.num_engines, scheduled_games);
+
+        let mut ranking: Vec<usize> = (0..self.num_engines).collect();
+        ranking.sort_by_key(|id| Reverse(scores[*id]));
+
+        if self.num_engines % 2 == 1 {
+            let bye_index = (0..ranking.len())
+                .rev()
+                .min_by_key(|index| byes[ranking[*index]])
+                .unwrap();
+            ranking.remove(bye_index);
+        }
+
+        let has_played = |id1: usize, id2: usize| {
+            scheduled_games.iter().any(|game| {
+                (game.white_engine_id.0, game.black_engine_id.0) == (id1, id2)
+                    || (game.white_engine_id.0, game.black_engine_id.0) == (id2, id1)
+            })
+        };
+        let pairs = pair_engines(&ranking, has_played)
+            .unwrap_or_else(|| pair_engines(&ranking, |_, _| false).unwrap());
+
+        // Number of times each engine had white in the first game of a pair
+        let mut first_whites = vec![0; self.num_engines];
+        for game in scheduled_games.iter().step_by(2) {
+            first_whites[game.white_engine_id.0] += 1;
+        }
+
+        let first_pair_number = scheduled_games.len() / 2;
+        pairs
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, (id1, id2))| {
+                let (white_id, black_id) = if first_whites[id2] < first_whites[id1] {
+                    (id2, id1)
+                } else {
+                    (id1, id2)
+                };
+                let pair_number = first_pair_number + i;
+                let opening =
+                    &self.openings[(self.openings_start_index + pair_number) % self.openings.len()];
+                // The second game of the pair is played with colors reversed
+                (0..2).map(move |j| ScheduledGame {
+                    round_number: pair_number * 2 + j,
+                    opening: opening.clone(),
+                    white_engine_id: EngineId(if j == 0 { white_id } else { black_id }),
+                    black_engine_id: EngineId(if j == 0 { black_id } else { white_id }),
+                    size: self.size,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Number of games in each round
+pub fn games_per_round(num_engines: usize) -> usize {
+    num_engines / 2 * 2
+}
+
+/// Each engine's score in half points, including byes
+pub fn scores<B: PgnPosition>(
+    num_engines: usize,
+    scheduled_games: &[ScheduledGame<B>],
+    finished_games: &[Option<Game<B>>],
+) -> Vec<u64> {
+    let mut scores: Vec<u64> = byes(num_engines, scheduled_games)
+        .into_iter()
+        .map(|num_byes| num_byes as u64 * 4)
+        .collect();
+    for (scheduled_game, game) in scheduled_games
+        .iter()
+        .zip(finished_games.iter())
+        .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
+    {
+        match game.game_result() {
+            Some(WhiteWin) => scores[scheduled_game.white_engine_id.0] += 2,
+            Some(BlackWin) => scores[scheduled_game.black_engine_id.0] += 2,
+            None | Some(Draw) => {
+                scores[scheduled_game.white_engine_id.0] += 1;
+                scores[scheduled_game.black_engine_id.0] += 1;
+            }
+        }
+    }
+    scores
+}
+
+/// Number of rounds each engine has sat out
+pub fn byes<B: PgnPosition>(
+    num_engines: usize,
+    scheduled_games: &[ScheduledGame<B>],
+) -> Vec<usize> {
+    let mut byes = vec![0; num_engines];
+    if num_engines % 2 == 0 {
+        return byes;
+    }
+    for round in scheduled_games.chunks(games_per_round(num_engines)) {
+        for (id, num_byes) in byes.iter_mut().enumerate() {
+            if !round
+                .iter()
+                .any(|game| game.white_engine_id.0 == id || game.black_engine_id.0 == id)
+            {
+                *num_byes += 1;
+            }
+        }
+    }
+    byes
+}
+
+/// Pair up engines in ranking order, so that each engine plays the highest-ranked available opponent it hasn't played yet.
+/// Backtracks when the remaining engines cannot be paired. Returns `None` if no such pairing was found
+pub(crate) fn pair_engines(
+    ranking: &[usize],
+    has_played: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<(usize, usize)>> {
+    let mut search_limit = PAIRING_SEARCH_LIMIT;
+    pair_remaining(ranking, &has_played, &mut search_limit)
+}
+
+fn pair_remaining(
+    unpaired: &[usize],
+    has_played: &impl Fn(usize, usize) -> bool,
+    search_limit: &mut usize,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&id, rest)) = unpaired.split_first() else {
+        return Some(vec![]);
+    };
+    for (i, &opponent_id) in rest.iter().enumerate() {
+        if has_played(id, opponent_id) {
+            continue;
+        }
+        if *search_limit == 0 {
+            return None;
+        }
+        *search_limit -= 1;
+        let remaining: Vec<usize> = rest
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, other_id)| *other_id)
+            .collect();
+        if let Some(mut pairs) = pair_remaining(&remaining, has_played, search_limit) {
+            pairs.insert(0, (id, opponent_id));
+            return Some(pairs);
+        }
+    }
+    None
+}
@@ -0,0 +1,192 @@
+use board_game_traits::Color;
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+use tiltak::position::Position;
+
+use crate::adjudication::{AdjudicationSettings, Adjudicator, DrawRule, ResignRule};
+use crate::game::Termination;
+use crate::resource_limits::ResourceLimit;
+use crate::uci::Score;
+
+#[test]
+fn max_plies_test() {
+    let settings = AdjudicationSettings {
+        max_plies: Some(4),
+        repetitions: None,
+        resign: None,
+        draw: None,
+    };
+    let position = Position::<5>::start_position();
+    let mut adjudicator = Adjudicator::new(&settings, &position);
+    for _ in 0..3 {
+        adjudicator.add_position(&position, None);
+        assert_eq!(adjudicator.adjudicate(), None);
+    }
+    adjudicator.add_position(&position, None);
+    assert_eq!(
+        adjudicator.adjudicate(),
+        Some(("1/2-1/2", Termination::MoveLimit(4)))
+    );
+}
+
+#[test]
+fn threefold_repetition_test() {
+    let settings = AdjudicationSettings::default();
+    let mut position = Position::<5>::start_position();
+    let mut adjudicator = Adjudicator::new(&settings, &position);
+
+    // Two placements, then shuffle both stones back and forth
+    for move_string in ["a1", "e5", "e5<", "a1>", "d5>", "b1<", "e5<", "a1>", "d5>"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        position.do_move(mv);
+        adjudicator.add_position(&position, None);
+        assert_eq!(
+            adjudicator.adjudicate(),
+            None,
+            "Adjudicated after {}",
+            move_string
+        );
+    }
+    let mv = position.move_from_san("b1<").unwrap();
+    position.do_move(mv);
+    adjudicator.add_position(&position, None);
+    assert_eq!(
+        adjudicator.adjudicate(),
+        Some(("1/2-1/2", Termination::Repetition(3)))
+    );
+}
+
+#[test]
+fn resign_test() {
+    let settings = AdjudicationSettings {
+        max_plies: None,
+        repetitions: None,
+        resign: Some(ResignRule {
+            moves: 2,
+            score: 500,
+        }),
+        draw: None,
+    };
+    let position = Position::<5>::start_position();
+    let mut adjudicator = Adjudicator::new(&settings, &position);
+
+    for score in [-600, -700, -550, 0, -600, -700, -800] {
+        adjudicator.add_position(&position, Some(Score::Cp(score)));
+        assert_eq!(adjudicator.adjudicate(), None);
+    }
+    adjudicator.add_position(&position, Some(Score::Cp(-900)));
+    assert_eq!(
+        adjudicator.adjudicate(),
+        Some(("0-1", Termination::Resignation(Color::White)))
+    );
+}
+
+#[test]
+fn resign_mate_score_test() {
+    let settings = AdjudicationSettings {
+        max_plies: None,
+        repetitions: None,
+        resign: Some(ResignRule {
+            moves: 1,
+            score: 500,
+        }),
+        draw: None,
+    };
+    let position = Position::<5>::start_position();
+    let mut adjudicator = Adjudicator::new(&settings, &position);
+
+    adjudicator.add_position(&position, Some(Score::Cp(200)));
+    adjudicator.add_position(&position, Some(Score::Mate(3)));
+    assert_eq!(adjudicator.adjudicate(), None);
+    adjudicator.add_position(&position, Some(Score::Mate(2)));
+    assert_eq!(
+        adjudicator.adjudicate(),
+        Some(("1-0", Termination::Resignation(Color::Black)))
+    );
+}
+
+#[test]
+fn score_draw_test() {
+    let settings = AdjudicationSettings {
+        max_plies: None,
+        repetitions: None,
+        resign: None,
+        draw: Some(DrawRule {
+            start_ply: 6,
+            moves: 1,
+            score: 10,
+        }),
+    };
+    let position = Position::<5>::start_position();
+    let mut adjudicator = Adjudicator::new(&settings, &position);
+
+    // The scores are drawish from the start, but the rule only applies after ply 6
+    for _ in 0..7 {
+        adjudicator.add_position(&position, Some(Score::Cp(0)));
+        assert_eq!(adjudicator.adjudicate(), None);
+    }
+    adjudicator.add_position(&position, Some(Score::Cp(5)));
+    assert_eq!(
+        adjudicator.adjudicate(),
+        Some(("1/2-1/2", Termination::ScoreDraw))
+    );
+}
+
+#[test]
+fn termination_kind_test() {
+    let terminations = [
+        (Termination::TimeForfeit(Color::White), "time"),
+        (Termination::Disconnect(Color::Black), "crash"),
+        (
+            Termination::ResourceLimit(Color::White, ResourceLimit::Memory),
+            "limit",
+        ),
+        (Termination::MalformedMove(Color::White), "malformed"),
+        (Termination::IllegalMove(Color::Black), "illegal"),
+        (Termination::MoveLimit(200), "max plies"),
+        (Termination::Repetition(3), "repetition"),
+        (Termination::Resignation(Color::White), "resign"),
+        (Termination::ScoreDraw, "score draw"),
+    ];
+    for (termination, kind) in terminations.iter() {
+        let tag = termination.to_string();
+        assert_eq!(tag.parse::<Termination>(), Ok(*termination));
+        assert_eq!(termination.kind(), *kind);
+    }
+    assert_eq!(
+        "White exceeded its cpu time limit".parse::<Termination>(),
+        Ok(Termination::ResourceLimit(
+            Color::White,
+            ResourceLimit::CpuTime
+        ))
+    );
+    // A crash with a memory limit is only likely to be from exceeding it
+    assert_eq!(
+        Termination::ResourceLimit(Color::Black, ResourceLimit::Memory).to_string(),
+        "Black crashed, likely from exceeding its memory limit"
+    );
+    assert_eq!(
+        "Adjudicated as a draw by 5-fold repetition".parse::<Termination>(),
+        Ok(Termination::Repetition(5))
+    );
+    assert_eq!(
+        "Game terminated after reaching 100 moves.".parse::<Termination>(),
+        Ok(Termination::MoveLimit(200))
+    );
+    // Only the exact text of each termination is recognized
+    for tag in [
+        "",
+        "White wins on time and more",
+        "Black disconnected",
+        "Red made an illegal move",
+        "Adjudicated as a draw after reaching x plies",
+        "Adjudicated as a draw after reaching +3 plies",
+        "Adjudicated as a draw by 3-fold repetition!",
+        "Adjudicated as a win for Black",
+        "Game terminated after reaching 100 moves",
+    ]
+    .iter()
+    {
+        assert!(tag.parse::<Termination>().is_err(), "Parsed \"{}\"", tag);
+    }
+}
@@ -2,9 +2,16 @@ use std::time::Duration;
 
 use tiltak::position::Komi;
 
+use crate::adjudication::{AdjudicationSettings, DrawRule, ResignRule};
+use crate::affinity;
 use crate::cli;
 use crate::cli::CliEngine;
+use crate::config;
+use crate::engine::{SearchLimits, StderrMode};
+use crate::game::AnnotationSettings;
+use crate::knockout::TieBreak;
 use crate::openings;
+use crate::resource_limits::{self, ResourceLimits};
 use crate::tournament::TournamentType;
 
 #[test]
@@ -24,24 +31,52 @@ fn cli_test() {
     let expected = cli::CliOptions {
         size: 6,
         concurrency: 10,
+        max_engines_per_worker: None,
+        affinity: None,
         games: 2000,
         engines: vec![
             CliEngine {
                 path: "tiltak".to_string(),
-                cli_args: None,
-                time: Duration::from_secs(60),
-                increment: Duration::from_millis(600),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
                 tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
             CliEngine {
                 path: "taktician".to_string(),
-                cli_args: Some("tei -multi-cut -table-mem 512000000".to_string()),
-                time: Duration::from_secs(60),
-                increment: Duration::from_millis(600),
+                name: None,
+                cli_args: vec![
+                    "tei".to_string(),
+                    "-multi-cut".to_string(),
+                    "-table-mem".to_string(),
+                    "512000000".to_string(),
+                ],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
                 tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
         ],
         pgnout: Some("tako_vs_tiltak.ptn".to_string()),
+        resume: false,
+        results_json: None,
+        report_interval: None,
+        periodic_stats: false,
+        fit_white_advantage: true,
+        opening_report: false,
+        filtered_book: None,
         book_path: Some("6s_4ply_balanced_openings.txt".to_string()),
         book_format: openings::BookFormat::MoveList,
         shuffle_book: true,
@@ -50,6 +85,11 @@ fn cli_test() {
         komi: Komi::default(),
         tournament_type: TournamentType::RoundRobin(2),
         sprt: None,
+        spsa: None,
+        timeout_margin: Duration::from_secs(5),
+        untimed_timeout: Duration::from_secs(60),
+        adjudication: AdjudicationSettings::default(),
+        annotation: AnnotationSettings::default(),
     };
 
     if let Err(err) = &cli_options {
@@ -70,24 +110,47 @@ fn shuffle_book_test() {
     let expected = cli::CliOptions {
         size: 5,
         concurrency: 1,
+        max_engines_per_worker: None,
+        affinity: None,
         games: 100,
         engines: vec![
             CliEngine {
                 path: "tiltak".to_string(),
-                cli_args: None,
-                time: Duration::from_secs(60),
-                increment: Duration::from_millis(600),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
                 tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
             CliEngine {
                 path: "taktician".to_string(),
-                cli_args: None,
-                time: Duration::from_secs(60),
-                increment: Duration::from_millis(600),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
                 tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
         ],
         pgnout: None,
+        resume: false,
+        results_json: None,
+        report_interval: None,
+        periodic_stats: false,
+        fit_white_advantage: true,
+        opening_report: false,
+        filtered_book: None,
         book_path: Some("openings.ptn".to_string()),
         book_format: openings::BookFormat::Pgn,
         shuffle_book: false,
@@ -96,6 +159,11 @@ fn shuffle_book_test() {
         komi: Komi::from_half_komi(5).unwrap(),
         tournament_type: TournamentType::RoundRobin(2),
         sprt: None,
+        spsa: None,
+        timeout_margin: Duration::from_secs(5),
+        untimed_timeout: Duration::from_secs(60),
+        adjudication: AdjudicationSettings::default(),
+        annotation: AnnotationSettings::default(),
     };
 
     if let Err(err) = &cli_options {
@@ -116,24 +184,47 @@ fn asymmetric_tc_test() {
     let expected = cli::CliOptions {
         size: 6,
         concurrency: 1,
+        max_engines_per_worker: None,
+        affinity: None,
         games: 10,
         engines: vec![
             CliEngine {
                 path: "tiltak".to_string(),
-                cli_args: None,
-                time: Duration::from_secs(60),
-                increment: Duration::from_secs(1),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_secs(1))),
+                    ..Default::default()
+                },
                 tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
             CliEngine {
                 path: "topaz".to_string(),
-                cli_args: None,
-                time: Duration::from_secs(180),
-                increment: Duration::from_secs(3),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(180), Duration::from_secs(3))),
+                    ..Default::default()
+                },
                 tei_settings: vec![("NN".to_string(), "topaz.txt".to_string())],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
             },
         ],
         pgnout: None,
+        resume: false,
+        results_json: None,
+        report_interval: None,
+        periodic_stats: false,
+        fit_white_advantage: true,
+        opening_report: false,
+        filtered_book: None,
         book_path: None,
         book_format: openings::BookFormat::MoveList,
         shuffle_book: false,
@@ -142,6 +233,11 @@ fn asymmetric_tc_test() {
         komi: Komi::from_half_komi(4).unwrap(),
         tournament_type: TournamentType::RoundRobin(2),
         sprt: None,
+        spsa: None,
+        timeout_margin: Duration::from_secs(5),
+        untimed_timeout: Duration::from_secs(60),
+        adjudication: AdjudicationSettings::default(),
+        annotation: AnnotationSettings::default(),
     };
 
     if let Err(err) = &cli_options {
@@ -150,3 +246,694 @@ fn asymmetric_tc_test() {
 
     assert_eq!(cli_options.unwrap(), expected)
 }
+
+#[test]
+fn search_limits_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 10 --engine path=tiltak depth=8 --engine path=topaz nodes=100000 movetime=0.5 --engine path=taktician tc=60 depth=10";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    let limits: Vec<SearchLimits> = cli_options
+        .engines
+        .iter()
+        .map(|engine| engine.limits)
+        .collect();
+
+    assert_eq!(
+        limits,
+        vec![
+            SearchLimits {
+                depth: Some(8),
+                ..Default::default()
+            },
+            SearchLimits {
+                nodes: Some(100000),
+                movetime: Some(Duration::from_millis(500)),
+                ..Default::default()
+            },
+            SearchLimits {
+                time_control: Some((Duration::from_secs(60), Duration::ZERO)),
+                depth: Some(10),
+                ..Default::default()
+            }
+        ]
+    );
+
+    let clock = (Duration::from_secs(30), Duration::from_millis(500));
+    assert_eq!(limits[0].go_string(clock, clock), "go depth 8");
+    assert_eq!(
+        limits[1].go_string(clock, clock),
+        "go nodes 100000 movetime 500"
+    );
+    assert_eq!(
+        limits[2].go_string(clock, clock),
+        "go wtime 30000 btime 30000 winc 500 binc 500 depth 10"
+    );
+
+    assert_eq!(limits[0].to_string(), "depth 8");
+    assert_eq!(limits[1].to_string(), "100000 nodes, 0.5s/move");
+    assert_eq!(limits[2].to_string(), "1:0 +0.0, depth 10");
+}
+
+#[test]
+fn adjudication_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 10 --all-engines tc=60+1 --engine path=tiltak --engine path=topaz --max-plies 300 --repetitions 0 --resign moves=4 score=1000 --draw ply=60 moves=8 score=15";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+
+    assert_eq!(
+        cli_options.adjudication,
+        AdjudicationSettings {
+            max_plies: Some(300),
+            repetitions: None,
+            resign: Some(ResignRule {
+                moves: 4,
+                score: 1000
+            }),
+            draw: Some(DrawRule {
+                start_ply: 60,
+                moves: 8,
+                score: 15
+            }),
+        }
+    )
+}
+
+#[test]
+fn config_file_test() {
+    let config = r#"
+size = 6
+games = 100
+concurrency = 4
+komi = 2
+book = "6s_4ply_balanced_openings.txt"
+annotate = ["nodes", "pv"]
+no-white-advantage = true
+
+[resign]
+moves = 4
+score = 1000
+
+[draw]
+ply = 60
+moves = 8
+score = 15
+
+[all-engines]
+tc = "60+0.6"
+
+[[engines]]
+path = "tiltak"
+name = "Tiltak"
+
+[[engines]]
+path = "topaz"
+option = { NN = "topaz.txt" }
+"#;
+    let config_path = std::env::temp_dir().join("racetrack_config_file_test.toml");
+    std::fs::write(&config_path, config).unwrap();
+
+    // The command-line arguments override the file
+    let input = format!(
+        "./racetrack --config {} --games 10 --draw moves=4 score=20",
+        config_path.to_str().unwrap()
+    );
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+
+    assert_eq!(cli_options.size, 6);
+    assert_eq!(cli_options.games, 10);
+    assert_eq!(cli_options.concurrency, 4);
+    assert!(!cli_options.fit_white_advantage);
+    assert_eq!(cli_options.komi, Komi::from_half_komi(4).unwrap());
+    assert_eq!(
+        cli_options.book_path,
+        Some("6s_4ply_balanced_openings.txt".to_string())
+    );
+    assert_eq!(
+        cli_options.annotation,
+        AnnotationSettings {
+            nodes: true,
+            pv: true,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        cli_options.adjudication.resign,
+        Some(ResignRule {
+            moves: 4,
+            score: 1000
+        })
+    );
+    assert_eq!(
+        cli_options.adjudication.draw,
+        Some(DrawRule {
+            start_ply: 0,
+            moves: 4,
+            score: 20
+        })
+    );
+    assert_eq!(
+        cli_options.engines,
+        vec![
+            CliEngine {
+                path: "tiltak".to_string(),
+                name: Some("Tiltak".to_string()),
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
+                tei_settings: vec![],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
+            },
+            CliEngine {
+                path: "topaz".to_string(),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits {
+                    time_control: Some((Duration::from_secs(60), Duration::from_millis(600))),
+                    ..Default::default()
+                },
+                tei_settings: vec![("NN".to_string(), "topaz.txt".to_string())],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn config_file_error_test() {
+    let config_path = std::env::temp_dir().join("racetrack_config_file_error_test.toml");
+    // Errors point to the line of the invalid setting
+    let configs = [
+        ("games = 10\nsize = 9\n", "line 2", "size"),
+        (
+            "games = 10\n\n[[engines]]\npath = \"tiltak\"\ntc = \"fast\"\n",
+            "line 5",
+            "tc",
+        ),
+        (
+            "games = 10\n\n[sprt]\nelo0 = 5\nelo1 = 0\n",
+            "line 3",
+            "elo1",
+        ),
+        ("games = 10\nformat = \"league\"\n", "line 2", "league"),
+        (
+            "games = 10\n\n[all-engines]\nthreads = 4\n",
+            "line 4",
+            "threads",
+        ),
+    ];
+    for (config, line, key) in configs.iter() {
+        std::fs::write(&config_path, config).unwrap();
+        let err = match config::read_config_file(config_path.to_str().unwrap()) {
+            Ok(_) => panic!("Config file was accepted:\n{}", config),
+            Err(err) => err,
+        };
+        assert!(err.contains(line), "Expected {} in {}", line, err);
+        assert!(err.contains(key), "Expected {} in {}", key, err);
+
+        // The error is returned from parsing the command line, rather than exiting
+        let input = format!("./racetrack --config {}", config_path.to_str().unwrap());
+        match cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())) {
+            Err(cli::CliError::Invalid(message)) => assert!(message.contains(line)),
+            result => panic!("Expected an invalid config error, got {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn invalid_settings_error_test() {
+    let inputs = [
+        (
+            "--sprt elo0=5 elo1=0 --format sprt",
+            "elo1 (0) must be greater than elo0 (5)",
+        ),
+        ("--resign moves=4", "Missing score for resign"),
+        ("--draw moves=4 score=x", "for draw score"),
+        ("--format knockout", "requires the --knockout argument"),
+        ("--format swiss", "at least 3 is required"),
+        ("--all-engines maxcpu=0", "maxcpu must be at least 1 second"),
+    ];
+    for (flags, expected) in inputs.iter() {
+        let input = format!(
+            "./racetrack --games 10 --engine path=tiltak tc=10+0.1 --engine path=topaz tc=10+0.1 {}",
+            flags
+        );
+        match cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())) {
+            Err(err @ cli::CliError::Invalid(_)) => {
+                let message = err.to_string();
+                assert!(message.starts_with("Error: "), "{}", message);
+                assert!(
+                    message.contains(expected),
+                    "Expected {} in {}",
+                    expected,
+                    message
+                );
+            }
+            result => panic!("Expected an error for {}, got {:?}", flags, result),
+        }
+    }
+}
+
+#[test]
+fn check_engine_test() {
+    let input = "./racetrack check-engine --engine path=tiltak option.Hash=64 --timeout 2.5";
+    let command =
+        cli::parse_command_from(input.split_whitespace().map(|word| word.into())).unwrap();
+
+    assert_eq!(
+        command,
+        cli::CliCommand::CheckEngine(cli::CheckEngineOptions {
+            engine: CliEngine {
+                path: "tiltak".to_string(),
+                name: None,
+                cli_args: vec![],
+                working_dir: None,
+                env_vars: vec![],
+                limits: SearchLimits::default(),
+                tei_settings: vec![("Hash".to_string(), "64".to_string())],
+                stderr: StderrMode::Inherit,
+                resource_limits: ResourceLimits::default(),
+            },
+            timeout: Duration::from_millis(2500),
+        })
+    );
+
+    let input = "./racetrack --games 2 --engine path=tiltak tc=10 --engine path=topaz tc=10";
+    let command =
+        cli::parse_command_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert!(matches!(command, cli::CliCommand::Tournament(_)));
+}
+
+#[test]
+fn stderr_mode_test() {
+    let input = "./racetrack --games 2 --all-engines tc=10 --engine path=tiltak stderr=log --engine path=topaz stderr=file:logs --engine path=taktician stderr=file";
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+
+    assert_eq!(cli_options.engines[0].stderr, StderrMode::Log);
+    assert_eq!(
+        cli_options.engines[1].stderr,
+        StderrMode::File("logs".to_string())
+    );
+    assert_eq!(
+        cli_options.engines[2].stderr,
+        StderrMode::File(".".to_string())
+    );
+
+    assert!("file:".parse::<StderrMode>().is_err());
+    assert!("stdout".parse::<StderrMode>().is_err());
+}
+
+#[test]
+fn annotate_test() {
+    let input = "./racetrack --games 2 --all-engines tc=10 --engine path=tiltak --engine path=topaz --annotate nodes pv clock";
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.annotation,
+        AnnotationSettings {
+            nodes: true,
+            nps: false,
+            seldepth: false,
+            pv: true,
+            clock: true,
+        }
+    );
+
+    let input = "./racetrack --games 2 --all-engines tc=10 --engine path=tiltak --engine path=topaz --annotate all";
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert!(cli_options.annotation.nps && cli_options.annotation.seldepth);
+
+    let input = "./racetrack --games 2 --all-engines tc=10 --engine path=tiltak --engine path=topaz --annotate eval";
+    assert!(
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).is_err()
+    );
+}
+
+#[test]
+fn swiss_test() {
+    let input: &str =
+        "./racetrack -s 6 --format swiss --rounds 4 --all-engines tc=60+1 --engine path=tiltak --engine path=topaz --engine path=taktician";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.tournament_type,
+        TournamentType::Swiss {
+            num_engines: 3,
+            rounds: 4
+        }
+    );
+    // One game pair per round, while the third engine sits out
+    assert_eq!(cli_options.games, 8);
+
+    let input: &str =
+        "./racetrack -s 6 --format swiss --rounds 4 --games 8 --all-engines tc=60+1 --engine path=tiltak --engine path=topaz --engine path=taktician";
+    assert!(
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).is_err()
+    );
+}
+
+#[test]
+fn knockout_test() {
+    let input: &str =
+        "./racetrack -s 6 --format knockout --knockout pairs=2 tiebreak=elo --all-engines tc=60+1 --engine path=tiltak --engine path=topaz --engine path=taktician --engine path=tiltak";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.tournament_type,
+        TournamentType::Knockout {
+            num_engines: 4,
+            game_pairs: 2,
+            tie_break: TieBreak::Elo
+        }
+    );
+    // Three matches of two game pairs each, not counting tie-breaks
+    assert_eq!(cli_options.games, 12);
+}
+
+#[test]
+fn report_interval_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 100 --report-interval 20 --all-engines tc=60+1 --engine path=tiltak --engine path=taktician";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(cli_options.report_interval, Some(20));
+
+    let input: &str =
+        "./racetrack -s 6 --games 100 --report-interval 0 --all-engines tc=60+1 --engine path=tiltak --engine path=taktician";
+    assert!(
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).is_err()
+    );
+}
+
+#[test]
+fn max_engines_per_worker_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 100 --concurrency 8 --max-engines-per-worker 3 --all-engines tc=60+1 --engine path=tiltak --engine path=topaz --engine path=taktician --engine path=tiltak";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(cli_options.max_engines_per_worker, Some(3));
+
+    // Each game needs two engines at once
+    let input: &str =
+        "./racetrack -s 6 --games 100 --max-engines-per-worker 1 --all-engines tc=60+1 --engine path=tiltak --engine path=taktician";
+    assert!(
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).is_err()
+    );
+}
+
+#[test]
+fn affinity_test() {
+    assert_eq!(
+        affinity::parse_core_list("0-3,8,10-11"),
+        Ok(vec![0, 1, 2, 3, 8, 10, 11])
+    );
+    assert!(affinity::parse_core_list("0-3,2").is_err());
+    assert!(affinity::parse_core_list("3-1").is_err());
+    assert!(affinity::parse_core_list("a").is_err());
+}
+
+/// Affinity is only supported on Linux, and is an error elsewhere
+#[cfg(target_os = "linux")]
+#[test]
+fn affinity_cli_test() {
+    use crate::affinity::AffinityPolicy;
+
+    let input: &str =
+        "./racetrack -s 6 --games 100 --concurrency 2 --affinity cores=4-7 policy=engine --all-engines tc=60+1 --engine path=tiltak --engine path=taktician";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    let affinity = cli_options.affinity.unwrap();
+    assert_eq!(affinity.policy, AffinityPolicy::PerEngine);
+    assert_eq!(affinity.engine_core(0, 1, 2), 5);
+    assert_eq!(affinity.engine_core(1, 0, 2), 6);
+    assert_eq!(affinity.worker_cores(1, 2), vec![6, 7]);
+
+    let input: &str =
+        "./racetrack -s 6 --games 100 --concurrency 4 --affinity cores=0-3 --all-engines tc=60+1 --engine path=tiltak --engine path=taktician";
+    let affinity = cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into()))
+        .unwrap()
+        .affinity
+        .unwrap();
+    assert_eq!(affinity.policy, AffinityPolicy::PerGame);
+    assert_eq!(affinity.engine_core(3, 0, 2), 3);
+    assert_eq!(affinity.engine_core(3, 1, 2), 3);
+}
+
+#[test]
+fn resource_limits_test() {
+    assert_eq!(resource_limits::parse_memory_size("512M"), Ok(512 << 20));
+    assert_eq!(resource_limits::parse_memory_size("4g"), Ok(4 << 30));
+    assert_eq!(resource_limits::parse_memory_size("1000"), Ok(1000));
+    assert!(resource_limits::parse_memory_size("0").is_err());
+    assert!(resource_limits::parse_memory_size("M").is_err());
+    assert!(resource_limits::parse_memory_size("1.5G").is_err());
+}
+
+/// Resource limits are only supported on Linux, and are an error elsewhere
+#[cfg(target_os = "linux")]
+#[test]
+fn resource_limits_cli_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 100 --all-engines tc=60+1 maxcpu=90 --engine path=tiltak maxmem=1G --engine path=taktician";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.engines[0].resource_limits,
+        ResourceLimits {
+            max_memory: Some(1 << 30),
+            max_cpu_seconds: Some(90),
+        }
+    );
+    assert_eq!(
+        cli_options.engines[1].resource_limits,
+        ResourceLimits {
+            max_memory: None,
+            max_cpu_seconds: Some(90),
+        }
+    );
+}
+
+#[test]
+fn working_dir_and_env_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 100 --all-engines tc=60+1 env.RUST_BACKTRACE=1 --engine path=topaz dir=engines/topaz env.OMP_NUM_THREADS=1 option.NN=topaz.txt --engine path=tiltak";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.engines[0].working_dir,
+        Some("engines/topaz".to_string())
+    );
+    assert_eq!(
+        cli_options.engines[0].env_vars,
+        vec![
+            ("OMP_NUM_THREADS".to_string(), "1".to_string()),
+            ("RUST_BACKTRACE".to_string(), "1".to_string())
+        ]
+    );
+    assert_eq!(cli_options.engines[1].working_dir, None);
+    assert_eq!(
+        cli_options.engines[1].env_vars,
+        vec![("RUST_BACKTRACE".to_string(), "1".to_string())]
+    );
+}
+
+#[test]
+fn split_arguments_test() {
+    assert_eq!(
+        cli::split_arguments("tei  -multi-cut\t-table-mem 512000000 "),
+        Ok(vec![
+            "tei".to_string(),
+            "-multi-cut".to_string(),
+            "-table-mem".to_string(),
+            "512000000".to_string()
+        ])
+    );
+    assert_eq!(
+        cli::split_arguments(r#"--nn "my file.txt" 'a b'c "d\"e\\" f\ g"#),
+        Ok(vec![
+            "--nn".to_string(),
+            "my file.txt".to_string(),
+            "a bc".to_string(),
+            "d\"e\\".to_string(),
+            "f g".to_string()
+        ])
+    );
+    // Single quotes keep double quotes and backslashes
+    assert_eq!(
+        cli::split_arguments(r#"'{"path": "C:\engines"}'"#),
+        Ok(vec![r#"{"path": "C:\engines"}"#.to_string()])
+    );
+    assert_eq!(
+        cli::split_arguments(r#"'' """#),
+        Ok(vec![String::new(), String::new()])
+    );
+    assert_eq!(cli::split_arguments(" "), Ok(vec![]));
+    assert!(cli::split_arguments(r#""unclosed"#).is_err());
+    assert!(cli::split_arguments("'unclosed").is_err());
+    assert!(cli::split_arguments(r"trailing\").is_err());
+
+    // Each arg is split and appended in order, after the engine's own args come the args for all engines.
+    // rawarg is a single argument
+    let input = "./racetrack --games 2 --engine path=tiltak tc=10";
+    let tail_input = [
+        "arg=tei -multi-cut",
+        "rawarg=my file.txt",
+        "--engine",
+        "path=taktician",
+        "tc=10",
+        "arg=tei -multi-cut",
+        "--all-engines",
+        "arg=--quiet",
+    ];
+    let cli_options = cli::parse_cli_arguments_from(
+        input
+            .split_whitespace()
+            .chain(tail_input)
+            .map(|word| word.into()),
+    )
+    .unwrap();
+    assert_eq!(
+        cli_options.engines[0].cli_args,
+        vec![
+            "tei".to_string(),
+            "-multi-cut".to_string(),
+            "my file.txt".to_string(),
+            "--quiet".to_string()
+        ]
+    );
+    assert_eq!(
+        cli_options.engines[1].cli_args,
+        vec![
+            "tei".to_string(),
+            "-multi-cut".to_string(),
+            "--quiet".to_string()
+        ]
+    );
+
+    // Repeated arg and rawarg on the same engine are appended in the order they are given.
+    // rawarg keeps quotes, backslashes and spaces as they are, and may be empty
+    let input = "./racetrack --games 2 --engine path=tiltak tc=10";
+    let tail_input = [
+        "arg=tei",
+        r#"rawarg={"path": "my dir/nn.bin"}"#,
+        r#"arg=--nn "my file.txt" -x\ y"#,
+        "rawarg='quoted' \\",
+        "rawarg=",
+        "arg=-q",
+        "--engine",
+        "path=taktician",
+        "tc=10",
+        "rawarg=tei",
+        "rawarg=tei",
+        "--all-engines",
+        "rawarg=a b",
+        "arg=a b",
+    ];
+    let cli_options = cli::parse_cli_arguments_from(
+        input
+            .split_whitespace()
+            .chain(tail_input)
+            .map(|word| word.into()),
+    )
+    .unwrap();
+    assert_eq!(
+        cli_options.engines[0].cli_args,
+        vec![
+            "tei".to_string(),
+            r#"{"path": "my dir/nn.bin"}"#.to_string(),
+            "--nn".to_string(),
+            "my file.txt".to_string(),
+            "-x y".to_string(),
+            "'quoted' \\".to_string(),
+            String::new(),
+            "-q".to_string(),
+            "a b".to_string(),
+            "a".to_string(),
+            "b".to_string()
+        ]
+    );
+    assert_eq!(
+        cli_options.engines[1].cli_args,
+        vec![
+            "tei".to_string(),
+            "tei".to_string(),
+            "a b".to_string(),
+            "a".to_string(),
+            "b".to_string()
+        ]
+    );
+
+    // An arg that can't be split is an error, while the same text is fine as a rawarg
+    let input = "./racetrack --games 2 --engine path=tiltak tc=10 --engine path=taktician tc=10";
+    for (tail, is_ok) in [("arg=\"unclosed", false), ("rawarg=\"unclosed", true)].iter() {
+        let result = cli::parse_cli_arguments_from(
+            input
+                .split_whitespace()
+                .chain([*tail])
+                .map(|word| word.into()),
+        );
+        match result {
+            Ok(cli_options) => {
+                assert!(is_ok);
+                assert_eq!(
+                    cli_options.engines[1].cli_args,
+                    vec!["\"unclosed".to_string()]
+                );
+            }
+            Err(err) => {
+                assert!(!is_ok);
+                assert!(err.to_string().contains("Unclosed quote"), "{}", err);
+            }
+        }
+    }
+
+    // In a config file, a string is split, while each element of an array is a single argument
+    let config = r#"
+games = 2
+
+[all-engines]
+tc = 10
+
+[[engines]]
+path = "tiltak"
+arg = "tei -multi-cut"
+
+[[engines]]
+path = "topaz"
+arg = ["--nn my.txt", "--quiet"]
+"#;
+    let config_path = std::env::temp_dir().join("racetrack_split_arguments_test.toml");
+    std::fs::write(&config_path, config).unwrap();
+    let input = format!("./racetrack --config {}", config_path.to_str().unwrap());
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+    assert_eq!(
+        cli_options.engines[0].cli_args,
+        vec!["tei".to_string(), "-multi-cut".to_string()]
+    );
+    assert_eq!(
+        cli_options.engines[1].cli_args,
+        vec!["--nn my.txt".to_string(), "--quiet".to_string()]
+    );
+}
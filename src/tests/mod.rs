@@ -1,6 +1,10 @@
+mod adjudication_tests;
 mod cli_tests;
+mod pgn_writer_tests;
+mod rating_tests;
 mod simulation_tests;
 mod sprt_tests;
+mod spsa_tests;
 mod uci_tests;
 
 mod tournament_tests;
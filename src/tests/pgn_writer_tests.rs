@@ -0,0 +1,163 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use board_game_traits::Position as PositionTrait;
+use tiltak::position::{Move, Position, Role, Square};
+use tiltak::ptn::{Game, PtnMove};
+
+use crate::pgn_writer::{self, PgnWriter};
+use crate::stats;
+use crate::uci::{Score, ScoreBound};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn empty_game() -> Game<Position<5>> {
+    Game {
+        start_position: Position::start_position(),
+        moves: vec![],
+        game_result_str: None,
+        tags: vec![("Round".to_string(), "3".to_string())],
+    }
+}
+
+#[test]
+fn games_are_written_in_order_test() {
+    let buffer = SharedBuffer::default();
+    let mut pgn_writer = PgnWriter::new(buffer.clone());
+
+    pgn_writer.submit_game(2, empty_game());
+    assert!(buffer.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn skipped_games_are_not_waited_for_test() {
+    let buffer = SharedBuffer::default();
+    let mut pgn_writer = PgnWriter::new(buffer.clone());
+
+    pgn_writer.skip_games([0, 1]);
+    pgn_writer.submit_game(2, empty_game());
+    assert!(!buffer.0.lock().unwrap().is_empty());
+}
+
+fn finished_game(round: usize) -> Game<Position<5>> {
+    Game {
+        start_position: Position::start_position(),
+        moves: (0..3)
+            .map(|i| PtnMove {
+                mv: Move::placement(Role::Flat, Square::from_u8(i)),
+                annotations: vec![],
+                comment: format!("move {}", i),
+            })
+            .collect(),
+        game_result_str: Some("1/2-1/2"),
+        tags: vec![("Round".to_string(), round.to_string())],
+    }
+}
+
+#[test]
+fn incomplete_last_game_is_dropped_test() {
+    let buffer = SharedBuffer::default();
+    let mut pgn_writer = PgnWriter::new(buffer.clone());
+    pgn_writer.submit_game(0, finished_game(1));
+    pgn_writer.submit_game(1, finished_game(2));
+    let ptn = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+
+    let (games, complete_length) = pgn_writer::parse_games::<Position<5>>(&ptn).unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(complete_length, ptn.len());
+
+    // Cut off the second game in its tags, and in its moves
+    let second_game_tag = ptn.rfind("[Round").unwrap();
+    let second_game_move = ptn.rfind("move 1").unwrap();
+    for cut in [second_game_tag + 3, second_game_move] {
+        let (games, complete_length) = pgn_writer::parse_games::<Position<5>>(&ptn[..cut]).unwrap();
+        assert_eq!(games.len(), 1);
+        assert!(games[0]
+            .tags
+            .iter()
+            .any(|(tag, value)| tag == "Round" && value == "1"));
+        assert!(complete_length <= second_game_tag);
+        assert_eq!(
+            pgn_writer::parse_games::<Position<5>>(&ptn[..complete_length])
+                .unwrap()
+                .0
+                .len(),
+            1
+        );
+    }
+}
+
+#[test]
+fn annotated_games_are_read_back_test() {
+    let comments = [
+        "+0.35/12 1.23s seldepth 18 nodes 123456 nps 100370 pv b1 c1 [%wclk 0:00:58.8] [%bclk 0:01:00.0]",
+        "-M2+/9 0.50s seldepth 9 nodes 200 nps 400 pv c1 [%wclk 0:00:58.8] [%bclk 0:00:59.5]",
+        "+12.00-/30 0.01s seldepth 31 nodes 1 nps 100 pv d1 [%wclk 0:00:59.3] [%bclk 0:00:59.5]",
+    ];
+    let mut game = finished_game(1);
+    for (ptn_move, comment) in game.moves.iter_mut().zip(comments) {
+        ptn_move.comment = comment.to_string();
+    }
+
+    let path = std::env::temp_dir().join("racetrack_annotated_games_test.ptn");
+    let mut pgn_writer = PgnWriter::new(std::fs::File::create(&path).unwrap());
+    pgn_writer.submit_game(0, game);
+    pgn_writer.submit_game(1, finished_game(2));
+    drop(pgn_writer);
+
+    let games = pgn_writer::games_from_file::<Position<5>>(path.to_str().unwrap()).unwrap();
+    assert_eq!(games.len(), 2);
+    let written_moves = finished_game(1).moves;
+    assert_eq!(games[0].moves.len(), written_moves.len());
+    for ((read_move, written_move), comment) in
+        games[0].moves.iter().zip(written_moves).zip(comments)
+    {
+        assert_eq!(read_move.mv, written_move.mv);
+        assert_eq!(read_move.comment, comment);
+    }
+    assert_eq!(games[0].game_result_str, Some("1/2-1/2"));
+    assert_eq!(
+        stats::move_time(&games[0].moves[1].comment),
+        Some(Duration::from_millis(500))
+    );
+    let score = games[0].moves[1].comment.split('/').next().unwrap();
+    assert_eq!(
+        Score::parse_with_bound(score).unwrap(),
+        (Score::Mate(-2), ScoreBound::Lower)
+    );
+}
+
+#[test]
+fn incomplete_last_game_is_moved_to_partial_file_test() {
+    let path = std::env::temp_dir().join("racetrack_partial_game_test.ptn");
+    let path_str = path.to_str().unwrap();
+    let partial_path = format!("{}.partial", path_str);
+    let mut pgn_writer = PgnWriter::new(std::fs::File::create(&path).unwrap());
+    pgn_writer.submit_game(0, finished_game(1));
+    pgn_writer.submit_game(1, finished_game(2));
+    drop(pgn_writer);
+
+    let ptn = std::fs::read_to_string(&path).unwrap();
+    let cut = ptn.rfind("move 1").unwrap();
+    std::fs::write(&path, &ptn[..cut]).unwrap();
+
+    let games = pgn_writer::games_from_file::<Position<5>>(path_str).unwrap();
+    assert_eq!(games.len(), 1);
+    let truncated = std::fs::read_to_string(&path).unwrap();
+    let partial = std::fs::read_to_string(&partial_path).unwrap();
+    assert_eq!(truncated.clone() + &partial, &ptn[..cut]);
+    assert!(partial.contains("[Round \"2\"]"));
+}
@@ -0,0 +1,63 @@
+use crate::rating::{self, fit_ratings};
+use crate::simulation::MatchScore;
+
+fn score(wins: u64, draws: u64, losses: u64) -> MatchScore {
+    MatchScore {
+        wins,
+        draws,
+        losses,
+    }
+}
+
+#[test]
+fn equal_engines_rating_test() {
+    let mut color_results = vec![vec![score(10, 20, 10); 3]; 3];
+    for (id, results) in color_results.iter_mut().enumerate() {
+        results[id] = MatchScore::default();
+    }
+    let ratings = fit_ratings(&color_results, true);
+    for id in 0..3 {
+        assert!(ratings.elos[id].abs() < 0.01, "{:?}", ratings);
+        assert!((ratings.los(id, (id + 1) % 3) - 0.5).abs() < 0.01);
+    }
+    assert!(ratings.white_advantage.abs() < 0.01);
+}
+
+#[test]
+fn ranked_engines_rating_test() {
+    let color_results = vec![
+        vec![score(0, 0, 0), score(30, 15, 5), score(40, 8, 2)],
+        vec![score(10, 15, 25), score(0, 0, 0), score(30, 12, 8)],
+        vec![score(5, 10, 35), score(12, 14, 24), score(0, 0, 0)],
+    ];
+    let ratings = fit_ratings(&color_results, true);
+    assert_eq!(ratings.ranking(), vec![0, 1, 2]);
+    assert!(ratings.elos.iter().sum::<f64>().abs() < 0.01);
+    assert!(ratings.white_advantage > 0.0);
+    assert!(ratings.los(0, 1) > 0.99);
+    assert!(ratings.los(2, 1) < 0.01);
+    for id in 0..3 {
+        assert!(ratings.error_bar(id) > 10.0 && ratings.error_bar(id) < 100.0);
+    }
+}
+
+#[test]
+fn perfect_score_rating_test() {
+    let color_results = vec![
+        vec![score(0, 0, 0), score(10, 0, 0), score(10, 0, 0)],
+        vec![score(0, 0, 10), score(0, 0, 0), score(5, 0, 5)],
+        vec![score(0, 0, 10), score(5, 0, 5), score(0, 0, 0)],
+    ];
+    let ratings = fit_ratings(&color_results, false);
+    assert!(ratings.elos.iter().all(|elo| elo.is_finite()));
+    assert_eq!(ratings.ranking()[0], 0);
+    assert!((ratings.elos[1] - ratings.elos[2]).abs() < 0.01);
+    assert_eq!(ratings.white_advantage, 0.0);
+}
+
+#[test]
+fn erf_test() {
+    assert!(rating::erf(0.0).abs() < 1e-6);
+    assert!((rating::erf(1.0) - 0.8427007).abs() < 1e-6);
+    assert!((rating::erf(-2.0) + 0.9953223).abs() < 1e-6);
+}
@@ -1,4 +1,4 @@
-use crate::simulation::{FullWinstonSimulation, MatchScore};
+use crate::simulation::{FullWinstonSimulation, MatchScore, ScoreEstimate};
 
 #[test]
 fn single_win_test() {
@@ -67,3 +67,38 @@ fn expected_score_near_the_center_of_the_distribution_test() {
         }
     }
 }
+
+#[test]
+fn normal_approximation_test() {
+    // With many games, the approximation is close to the simulation
+    let score = MatchScore {
+        wins: 300,
+        draws: 250,
+        losses: 250,
+    };
+    let simulated = ScoreEstimate::new(score);
+    let approximated = ScoreEstimate::normal_approximation(score);
+    assert_eq!(approximated.expected, simulated.expected);
+    assert!(
+        (approximated.lower - simulated.lower).abs() < 0.005,
+        "Lower bound was {:.3}, simulated {:.3}",
+        approximated.lower,
+        simulated.lower
+    );
+    assert!(
+        (approximated.upper - simulated.upper).abs() < 0.005,
+        "Upper bound was {:.3}, simulated {:.3}",
+        approximated.upper,
+        simulated.upper
+    );
+
+    let all_wins = ScoreEstimate::normal_approximation(MatchScore {
+        wins: 10,
+        draws: 0,
+        losses: 0,
+    });
+    assert_eq!(all_wins.upper, 1.0);
+    assert!(ScoreEstimate::normal_approximation(MatchScore::default())
+        .expected
+        .is_nan());
+}
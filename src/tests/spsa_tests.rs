@@ -0,0 +1,76 @@
+use board_game_traits::GameResult;
+
+use crate::cli;
+use crate::spsa::{Spsa, SpsaSettings};
+use crate::tournament::TournamentType;
+
+fn spsa() -> Spsa {
+    let mut spsa = Spsa::new(
+        SpsaSettings::new(vec![("Foo".to_string(), 4.0), ("Bar".to_string(), 10.0)]),
+        100,
+    );
+    spsa.add_parameter("Foo", 50, 0, 100);
+    spsa.add_parameter("Bar", 1000, 990, 2000);
+    spsa
+}
+
+#[test]
+fn spsa_perturbation_test() {
+    let mut spsa = spsa();
+    let perturbation = spsa.perturbation(0);
+
+    // Both games in a pair use the same values
+    assert_eq!(spsa.perturbation(0), perturbation);
+
+    let (foo_plus, foo_minus) = (perturbation.plus[0].1, perturbation.minus[0].1);
+    assert_eq!(foo_plus + foo_minus, 100);
+    assert!(foo_plus != 50);
+
+    // Perturbations are clamped to the option's bounds
+    for (_, value) in perturbation.plus.iter().chain(perturbation.minus.iter()) {
+        assert!(*value >= 0 && *value <= 2000);
+    }
+    assert!(perturbation.minus[1].1 >= 990 && perturbation.plus[1].1 >= 990);
+}
+
+#[test]
+fn spsa_update_test() {
+    let mut spsa = spsa();
+    let perturbation = spsa.perturbation(0);
+
+    // The perturbed engine wins both games
+    spsa.record_game(0, true, GameResult::WhiteWin).unwrap();
+    assert_eq!(spsa.iterations_finished(), 0);
+    spsa.record_game(0, false, GameResult::BlackWin).unwrap();
+    assert_eq!(spsa.iterations_finished(), 1);
+
+    // The parameter moves towards the winning engine's value
+    let foo = spsa.parameters()[0].value;
+    if perturbation.plus[0].1 > 50 {
+        assert!(foo > 50.0);
+    } else {
+        assert!(foo < 50.0);
+    }
+
+    // A drawn pair leaves the parameters unchanged
+    let parameters = spsa.parameters().to_vec();
+    spsa.perturbation(1);
+    spsa.record_game(1, true, GameResult::WhiteWin).unwrap();
+    spsa.record_game(1, true, GameResult::BlackWin).unwrap();
+    assert_eq!(spsa.parameters(), parameters.as_slice());
+}
+
+#[test]
+fn spsa_cli_test() {
+    let input: &str =
+        "./racetrack -s 6 --games 1000 --format spsa --engine path=tiltak tc=10+0.1 --spsa param.Foo=4 param.Bar=2.5 r=0.001 log=tune.csv";
+
+    let cli_options =
+        cli::parse_cli_arguments_from(input.split_whitespace().map(|word| word.into())).unwrap();
+
+    assert_eq!(cli_options.tournament_type, TournamentType::Spsa);
+    let mut expected = SpsaSettings::new(vec![("Foo".to_string(), 4.0), ("Bar".to_string(), 2.5)]);
+    expected.learning_rate = 0.001;
+    expected.log_path = "tune.csv".to_string();
+    assert_eq!(cli_options.spsa, Some(expected));
+}
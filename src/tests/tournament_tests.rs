@@ -1,13 +1,30 @@
-use std::{io, num::NonZeroUsize, sync::Mutex};
+use std::{
+    io,
+    num::NonZeroUsize,
+    sync::{atomic::AtomicBool, Mutex},
+    time::Duration,
+};
 
-use board_game_traits::Position as PositionTrait;
+use board_game_traits::{Color, Position as PositionTrait};
 use tiltak::position::{Move, Position, Role, Square};
+use tiltak::ptn::{Game, PtnMove};
 
 use crate::{
-    game::ScheduledGame,
+    adjudication::AdjudicationSettings,
+    game::{AnnotationSettings, ScheduledGame, Termination},
+    knockout::{seeding_order, BracketEntry, Knockout, TieBreak},
+    opening_report::{self, OpeningResult},
     openings::Opening,
     pgn_writer::PgnWriter,
-    tournament::{EngineId, TournamentSettings, TournamentType},
+    progress::{self, Progress},
+    simulation::MatchScore,
+    sprt::PentanomialResult,
+    stats::{self, GameStats},
+    swiss::{self, Swiss},
+    tournament::{
+        pair_penta_stats, restore_games, unique_names, EngineId, Tournament, TournamentSettings,
+        TournamentType,
+    },
 };
 
 fn dummy_tournament(
@@ -29,6 +46,17 @@ fn dummy_tournament(
         pgn_writer: Mutex::new(PgnWriter::new(io::empty())),
         tournament_type,
         sprt: None,
+        spsa: None,
+        adjudication: AdjudicationSettings::default(),
+        annotation: AnnotationSettings::default(),
+        results_json: None,
+        report_interval: None,
+        periodic_stats: false,
+        fit_white_advantage: true,
+        opening_report: None,
+        max_engines_per_worker: None,
+        affinity: None,
+        resumed_games: vec![],
     }
 }
 
@@ -269,3 +297,520 @@ fn book_test_n_engines_test() {
         }
     }
 }
+
+#[test]
+fn sprt_pentanomial_test() {
+    let settings: TournamentSettings<Position<6>> =
+        dummy_tournament(6, TournamentType::Sprt(NonZeroUsize::new(1).unwrap()));
+    let schedule = settings.schedule();
+
+    let game = |result: &'static str| {
+        Some(Game {
+            start_position: Position::start_position(),
+            moves: vec![],
+            game_result_str: Some(result),
+            tags: vec![],
+        })
+    };
+    let finished_games = vec![
+        // Engine 1 wins with both colors
+        game("0-1"),
+        game("1-0"),
+        // Engine 1 draws with black, and loses with white
+        game("1/2-1/2"),
+        game("0-1"),
+        // Unfinished pair
+        game("1-0"),
+        None,
+    ];
+
+    assert_eq!(
+        pair_penta_stats(&schedule, &finished_games, 1, 0),
+        PentanomialResult {
+            ww: 1,
+            dl: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        pair_penta_stats(&schedule, &finished_games, 0, 1),
+        PentanomialResult {
+            ll: 1,
+            wd: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn unique_names_test() {
+    let names = ["tiltak", "topaz", "tiltak", "taktician", "tiltak"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        unique_names(names),
+        vec![
+            "tiltak (1)",
+            "topaz",
+            "tiltak (2)",
+            "taktician",
+            "tiltak (3)"
+        ]
+    );
+}
+
+fn finished_game(result: &'static str) -> Option<Game<Position<6>>> {
+    Some(Game {
+        start_position: Position::start_position(),
+        moves: vec![],
+        game_result_str: Some(result),
+        tags: vec![],
+    })
+}
+
+#[test]
+fn swiss_rounds_test() {
+    let settings: TournamentSettings<Position<6>> = dummy_tournament(
+        12,
+        TournamentType::Swiss {
+            num_engines: 4,
+            rounds: 3,
+        },
+    );
+    let mut schedule = settings.schedule();
+    let pairings = |games: &[ScheduledGame<Position<6>>]| -> Vec<(usize, usize, usize)> {
+        games
+            .iter()
+            .map(|game| {
+                (
+                    game.round_number,
+                    game.white_engine_id.0,
+                    game.black_engine_id.0,
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        pairings(&schedule),
+        vec![(0, 0, 1), (1, 1, 0), (2, 2, 3), (3, 3, 2)]
+    );
+    // Each pair shares an opening
+    assert_eq!(schedule[0].opening, schedule[1].opening);
+    assert_ne!(schedule[1].opening, schedule[2].opening);
+
+    // Engine 0 wins both games, engines 2 and 3 draw both
+    let finished_games = vec![
+        finished_game("1-0"),
+        finished_game("0-1"),
+        finished_game("1/2-1/2"),
+        finished_game("1/2-1/2"),
+    ];
+    assert_eq!(
+        swiss::scores(4, &schedule, &finished_games),
+        vec![4, 0, 2, 2]
+    );
+
+    let swiss = Swiss::new(4, 3, settings.openings.clone(), 0, 6);
+    let next_round = swiss.next_round(&schedule, &finished_games);
+    // The leader plays the highest-ranked engine it hasn't played yet,
+    // and engines that had black in the first game of their last pair start with white
+    assert_eq!(
+        pairings(&next_round),
+        vec![(4, 0, 2), (5, 2, 0), (6, 3, 1), (7, 1, 3)]
+    );
+    assert_eq!(next_round[0].opening, settings.openings[2]);
+    schedule.extend(next_round);
+    assert_eq!(schedule.len(), 8);
+
+    // No games are scheduled after the last round
+    let finished_games = vec![finished_game("1/2-1/2"); 8];
+    let last_round = swiss.next_round(&schedule, &finished_games);
+    assert_eq!(last_round.len(), 4);
+    schedule.extend(last_round);
+    let finished_games = vec![finished_game("1/2-1/2"); 12];
+    assert!(swiss.next_round(&schedule, &finished_games).is_empty());
+}
+
+#[test]
+fn swiss_bye_test() {
+    let settings: TournamentSettings<Position<6>> = dummy_tournament(
+        6,
+        TournamentType::Swiss {
+            num_engines: 3,
+            rounds: 3,
+        },
+    );
+    let schedule = settings.schedule();
+    assert_eq!(schedule.len(), 2);
+    assert_eq!(swiss::byes(3, &schedule), vec![0, 0, 1]);
+
+    // The bye is scored as two wins
+    let finished_games = vec![finished_game("1-0"), finished_game("0-1")];
+    assert_eq!(swiss::scores(3, &schedule, &finished_games), vec![4, 0, 4]);
+
+    // The bye goes to the lowest-ranked engine without one
+    let swiss = Swiss::new(3, 3, settings.openings.clone(), 0, 6);
+    let next_round = swiss.next_round(&schedule, &finished_games);
+    assert_eq!(next_round.len(), 2);
+    assert!(next_round
+        .iter()
+        .all(|game| game.white_engine_id != EngineId(1) && game.black_engine_id != EngineId(1)));
+}
+
+#[test]
+fn swiss_pairing_test() {
+    let played = |pairs: &'static [(usize, usize)]| {
+        move |id1: usize, id2: usize| {
+            pairs
+                .iter()
+                .any(|pair| *pair == (id1, id2) || *pair == (id2, id1))
+        }
+    };
+    assert_eq!(
+        swiss::pair_engines(&[0, 1, 2, 3], played(&[])),
+        Some(vec![(0, 1), (2, 3)])
+    );
+    // Pairing 0 with 1 leaves 2 and 3, who have already played
+    assert_eq!(
+        swiss::pair_engines(&[0, 1, 2, 3], played(&[(2, 3), (0, 2)])),
+        Some(vec![(0, 3), (1, 2)])
+    );
+    assert_eq!(
+        swiss::pair_engines(&[0, 1, 2, 3], played(&[(0, 1), (0, 2), (0, 3)])),
+        None
+    );
+}
+
+#[test]
+fn seeding_order_test() {
+    assert_eq!(seeding_order(2), vec![0, 1]);
+    assert_eq!(seeding_order(4), vec![0, 3, 1, 2]);
+    assert_eq!(seeding_order(8), vec![0, 7, 3, 4, 1, 6, 2, 5]);
+}
+
+#[test]
+fn knockout_test() {
+    let settings: TournamentSettings<Position<6>> = dummy_tournament(
+        4,
+        TournamentType::Knockout {
+            num_engines: 3,
+            game_pairs: 1,
+            tie_break: TieBreak::ExtraPairs,
+        },
+    );
+    let mut knockout = Knockout::new(
+        3,
+        1,
+        TieBreak::ExtraPairs,
+        true,
+        settings.openings.clone(),
+        0,
+        6,
+    );
+    let mut schedule = knockout.next_round(&[], &[]);
+    let pairings = |games: &[ScheduledGame<Position<6>>]| -> Vec<(usize, usize)> {
+        games
+            .iter()
+            .map(|game| (game.white_engine_id.0, game.black_engine_id.0))
+            .collect()
+    };
+    // The top seed gets a bye
+    assert_eq!(pairings(&schedule), vec![(1, 2), (2, 1)]);
+    assert_eq!(knockout.bracket()[0][0], BracketEntry::Bye(0));
+
+    // A tied match continues with an extra pair
+    let mut finished_games = vec![finished_game("1/2-1/2"), finished_game("1/2-1/2")];
+    let extra_pair = knockout.next_round(&schedule, &finished_games);
+    assert_eq!(pairings(&extra_pair), vec![(1, 2), (2, 1)]);
+    assert_eq!(extra_pair[0].round_number, 2);
+    assert_ne!(extra_pair[0].opening, schedule[0].opening);
+    schedule.extend(extra_pair);
+
+    // Engine 1 wins the extra pair, and plays the top seed in the final
+    finished_games.extend([finished_game("1-0"), finished_game("1/2-1/2")]);
+    let final_round = knockout.next_round(&schedule, &finished_games);
+    assert_eq!(knockout.bracket()[0][1].winner(), Some(1));
+    assert_eq!(pairings(&final_round), vec![(0, 1), (1, 0)]);
+    schedule.extend(final_round);
+
+    // Each engine wins with black, and then the extra pair is drawn.
+    // The engines' ratings are equal, so the higher seed wins
+    finished_games.extend([finished_game("0-1"), finished_game("0-1")]);
+    let extra_pair = knockout.next_round(&schedule, &finished_games);
+    assert_eq!(extra_pair.len(), 2);
+    schedule.extend(extra_pair);
+    finished_games.extend([finished_game("1/2-1/2"), finished_game("1/2-1/2")]);
+    assert!(knockout.next_round(&schedule, &finished_games).is_empty());
+    assert_eq!(knockout.champion(), Some(0));
+    let BracketEntry::Match(final_match) = &knockout.bracket()[1][0] else {
+        panic!("Expected a match in the final")
+    };
+    assert!(final_match.decided_by_elo);
+    assert_eq!(final_match.scores(&schedule, &finished_games), [4, 4]);
+}
+
+#[test]
+fn multi_candidate_sprt_test() {
+    let settings: TournamentSettings<Position<6>> =
+        dummy_tournament(12, TournamentType::Sprt(NonZeroUsize::new(3).unwrap()));
+    let schedule = settings.schedule();
+
+    let pairings: Vec<(usize, usize)> = schedule
+        .iter()
+        .map(|game| (game.white_engine_id.0, game.black_engine_id.0))
+        .collect();
+    assert_eq!(
+        pairings,
+        vec![
+            (0, 1),
+            (1, 0),
+            (0, 2),
+            (2, 0),
+            (0, 3),
+            (3, 0),
+            (0, 1),
+            (1, 0),
+            (0, 2),
+            (2, 0),
+            (0, 3),
+            (3, 0)
+        ]
+    );
+    // Every candidate plays the same openings
+    assert_eq!(schedule[0].opening, schedule[5].opening);
+    assert_ne!(schedule[5].opening, schedule[6].opening);
+    assert_eq!(schedule[6].opening, schedule[11].opening);
+
+    // Each candidate gets its own pentanomial results
+    let finished_games: Vec<_> = ["0-1", "1-0", "1/2-1/2", "1/2-1/2", "1-0", "0-1"]
+        .iter()
+        .map(|result| finished_game(result))
+        .chain(vec![None; 6])
+        .collect();
+    assert_eq!(
+        pair_penta_stats(&schedule, &finished_games, 1, 0),
+        PentanomialResult {
+            ww: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        pair_penta_stats(&schedule, &finished_games, 2, 0),
+        PentanomialResult {
+            dd: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        pair_penta_stats(&schedule, &finished_games, 3, 0),
+        PentanomialResult {
+            ll: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn progress_eta_test() {
+    let mut progress = Progress::new(4);
+    assert_eq!(progress.eta(10), None);
+    progress.record_game(Duration::from_secs(60));
+    assert_eq!(progress.record_game(Duration::from_secs(120)), 2);
+
+    // 8 games at 90s each, 4 at a time
+    assert_eq!(progress.eta(8), Some(Duration::from_secs(180)));
+    // Fewer games left than threads
+    assert_eq!(progress.eta(2), Some(Duration::from_secs(90)));
+    assert_eq!(progress.eta(0), Some(Duration::ZERO));
+
+    assert_eq!(progress::format_duration(Duration::from_secs(12)), "12s");
+    assert_eq!(progress::format_duration(Duration::from_secs(185)), "3m05s");
+    assert_eq!(
+        progress::format_duration(Duration::from_secs(3720)),
+        "1h02m"
+    );
+}
+
+#[test]
+fn game_stats_test() {
+    let settings: TournamentSettings<Position<6>> =
+        dummy_tournament(4, TournamentType::RoundRobin(2));
+    let schedule = settings.schedule();
+
+    let ptn_move = |i: u8, comment: &str| PtnMove {
+        mv: Move::placement(Role::Flat, Square::from_u8(i)),
+        annotations: vec![],
+        comment: comment.to_string(),
+    };
+    let mut road_win = finished_game("R-0");
+    road_win.as_mut().unwrap().moves = vec![
+        ptn_move(0, ""),
+        ptn_move(1, "-0.20/9 0.50s"),
+        ptn_move(2, "0.30/10 1.50s seldepth 12"),
+        ptn_move(3, "0.50s [%wclk 0:00:58.5]"),
+    ];
+    let mut time_forfeit = finished_game("1-0");
+    time_forfeit.as_mut().unwrap().tags = vec![(
+        "Termination".to_string(),
+        Termination::TimeForfeit(Color::Black).to_string(),
+    )];
+    let mut long_draw = finished_game("1/2-1/2");
+    long_draw.as_mut().unwrap().moves = (0..10).map(|i| ptn_move(i, "")).collect();
+    let finished_games = vec![road_win, time_forfeit, long_draw, finished_game("0-F")];
+
+    let stats = GameStats::new(2, &schedule, &finished_games);
+    assert_eq!(stats.length_histogram, vec![3, 1]);
+
+    // The first engine won the road and flat wins, and lost on time
+    assert_eq!(
+        stats.engines[0].terminations,
+        vec![("road", 1), ("time", 1), ("draw", 1), ("flat", 1)]
+    );
+    assert_eq!(stats.engines[1].terminations, vec![("draw", 1)]);
+    assert_eq!(
+        stats.engines[0].white_score,
+        MatchScore {
+            wins: 1,
+            draws: 1,
+            losses: 0
+        }
+    );
+    assert_eq!(
+        stats.engines[0].black_score,
+        MatchScore {
+            wins: 1,
+            draws: 0,
+            losses: 1
+        }
+    );
+    assert_eq!(
+        stats.engines[0].average_move_time(),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(
+        stats.engines[1].average_move_time(),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(stats.engines[1].timed_moves, 2);
+    assert_eq!(stats::move_time(""), None);
+    assert_eq!(stats::move_time("[%wclk 0:00:58.5]"), None);
+    assert_eq!(stats::move_time("+0.10/5 -1s"), None);
+    assert_eq!(stats::move_time("NaNs"), None);
+    assert_eq!(stats::move_time("+0.10/5 infs"), None);
+}
+
+#[test]
+fn opening_results_test() {
+    let settings: TournamentSettings<Position<6>> =
+        dummy_tournament(6, TournamentType::RoundRobin(2));
+    let schedule = settings.schedule();
+    let finished_games = vec![
+        finished_game("R-0"),
+        finished_game("F-0"),
+        finished_game("1-0"),
+        finished_game("0-R"),
+        finished_game("1/2-1/2"),
+        None,
+    ];
+
+    let results = opening_report::opening_results(&settings.openings, &schedule, &finished_games);
+    assert_eq!(
+        results,
+        vec![
+            OpeningResult {
+                index: 0,
+                white_score: MatchScore {
+                    wins: 2,
+                    draws: 0,
+                    losses: 0
+                },
+                game_pairs: 1,
+                lopsided_pairs: 1,
+            },
+            OpeningResult {
+                index: 1,
+                white_score: MatchScore {
+                    wins: 1,
+                    draws: 0,
+                    losses: 1
+                },
+                game_pairs: 1,
+                lopsided_pairs: 0,
+            },
+            // The second game of the pair is unfinished
+            OpeningResult {
+                index: 2,
+                white_score: MatchScore {
+                    wins: 0,
+                    draws: 1,
+                    losses: 0
+                },
+                game_pairs: 0,
+                lopsided_pairs: 0,
+            },
+        ]
+    );
+    assert!(results[0].is_lopsided());
+    assert!(!results[1].is_lopsided());
+}
+
+fn resumed_game(round: usize, opening: &Opening<Position<6>>) -> Game<Position<6>> {
+    Game {
+        start_position: opening.root_position.clone(),
+        moves: opening
+            .moves
+            .iter()
+            .cloned()
+            .map(|mv| PtnMove {
+                mv,
+                annotations: vec![],
+                comment: String::new(),
+            })
+            .collect(),
+        game_result_str: Some("1-0"),
+        tags: vec![("Round".to_string(), round.to_string())],
+    }
+}
+
+#[test]
+fn resume_test() {
+    let mut settings = dummy_tournament(4, TournamentType::RoundRobin(2));
+    let scheduled_games = settings.schedule();
+    // Games #1 and #2 have the first opening, and games #3 and #4 have the second
+    assert_ne!(scheduled_games[1].opening, scheduled_games[2].opening);
+
+    settings.resumed_games = vec![
+        resumed_game(3, &scheduled_games[2].opening),
+        resumed_game(1, &scheduled_games[0].opening),
+        // Games without a round tag, or outside the tournament, are ignored
+        Game {
+            tags: vec![],
+            ..resumed_game(2, &scheduled_games[1].opening)
+        },
+        resumed_game(10, &scheduled_games[0].opening),
+    ];
+    let tournament = Tournament::new(settings);
+
+    // The restored games are not played again
+    let is_shutting_down = AtomicBool::new(false);
+    let mut unplayed_games = vec![];
+    while let Some(game) = tournament.next_unplayed_game(&is_shutting_down) {
+        unplayed_games.push(game.round_number);
+    }
+    assert_eq!(unplayed_games, vec![1, 3]);
+
+    // A game with a different opening is from a different tournament
+    assert!(restore_games(
+        &scheduled_games,
+        vec![resumed_game(2, &scheduled_games[2].opening)]
+    )
+    .is_err());
+    let finished_games = restore_games(
+        &scheduled_games,
+        vec![resumed_game(2, &scheduled_games[1].opening)],
+    )
+    .unwrap();
+    assert!(finished_games[1].is_some());
+    assert_eq!(finished_games.iter().flatten().count(), 1);
+}
@@ -1,5 +1,8 @@
-use crate::uci::{parser, UciOption, UciOptionType};
+use crate::uci::{parser, Score, ScoreBound, UciOption, UciOptionType};
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
 use std::time::Duration;
+use tiltak::position::Position;
 
 #[test]
 fn parse_check_option_description() {
@@ -75,3 +78,138 @@ fn parse_tc_test() {
         (Duration::from_millis(500), Duration::from_millis(100))
     );
 }
+
+#[test]
+fn parse_movetime_test() {
+    assert_eq!(
+        parser::parse_movetime("0.5").unwrap(),
+        Duration::from_millis(500)
+    );
+    assert!(parser::parse_movetime("0").is_err());
+    assert!(parser::parse_movetime("-1").is_err());
+    assert!(parser::parse_movetime("0.0004").is_err());
+    assert!(parser::parse_movetime("inf").is_err());
+    assert_eq!(
+        parser::parse_movetime("0.001").unwrap(),
+        Duration::from_millis(1)
+    );
+}
+
+#[test]
+fn parse_info_string_test() {
+    let position = <Position<5>>::start_position();
+    let info = parser::parse_info_string(
+        "info depth 7 seldepth 12 multipv 2 score cp -35 nodes 120000 nps 240000 time 500 hashfull 123 pv a1 e5 c3",
+        &position,
+    )
+    .unwrap();
+
+    assert_eq!(info.depth, 7);
+    assert_eq!(info.seldepth, 12);
+    assert_eq!(info.multipv, 2);
+    assert_eq!(info.score, Score::Cp(-35));
+    assert_eq!(info.bound, ScoreBound::Exact);
+    assert_eq!(info.nodes, 120000);
+    assert_eq!(info.nps, 240000);
+    assert_eq!(info.time, 500);
+    assert_eq!(info.hashfull, 0.123);
+    assert_eq!(
+        info.pv,
+        ["a1", "e5", "c3"]
+            .iter()
+            .map(|mv| position.move_from_lan(mv).unwrap())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn parse_info_string_pv_test() {
+    let position = <Position<5>>::start_position();
+
+    // The pv is cut off at the first illegal move
+    let info = parser::parse_info_string("info depth 2 score cp 0 pv a1 a1 c3", &position).unwrap();
+    assert_eq!(info.pv.len(), 1);
+
+    let info = parser::parse_info_string("info score cp 0 pv a1 x9 c3", &position).unwrap();
+    assert_eq!(info.pv.len(), 1);
+
+    // Nps is calculated if the engine does not send it
+    let info = parser::parse_info_string("info score cp 0 nodes 5000 time 250", &position).unwrap();
+    assert_eq!(info.nps, 20000);
+    // A huge node count doesn't overflow
+    let info = parser::parse_info_string(
+        &format!("info score cp 0 nodes {} time 1000", u64::MAX),
+        &position,
+    )
+    .unwrap();
+    assert_eq!(info.nps, u64::MAX / 1000);
+
+    assert!(parser::parse_info_string("info depth x score cp 0", &position).is_err());
+    assert!(parser::parse_info_string("info depth 3 nodes 100", &position).is_err());
+    assert!(parser::parse_info_string("info string score cp 5", &position).is_err());
+}
+
+#[test]
+fn parse_info_score_test() {
+    let position = <Position<5>>::start_position();
+
+    let info = parser::parse_info_string("info depth 9 score mate 3 pv a1", &position).unwrap();
+    assert_eq!(info.score, Score::Mate(3));
+    assert_eq!(info.bound, ScoreBound::Exact);
+    assert_eq!(info.pv.len(), 1);
+
+    let info = parser::parse_info_string("info score mate -2", &position).unwrap();
+    assert_eq!(info.score, Score::Mate(-2));
+
+    let info =
+        parser::parse_info_string("info depth 4 score cp 50 lowerbound nodes 100", &position)
+            .unwrap();
+    assert_eq!(info.score, Score::Cp(50));
+    assert_eq!(info.bound, ScoreBound::Lower);
+    assert_eq!(info.nodes, 100);
+
+    let info = parser::parse_info_string("info score cp -20 upperbound", &position).unwrap();
+    assert_eq!(info.bound, ScoreBound::Upper);
+
+    assert!(parser::parse_info_string("info score wdl 500 300 200", &position).is_err());
+    assert!(parser::parse_info_string("info score mate", &position).is_err());
+}
+
+#[test]
+fn score_test() {
+    for score in [
+        Score::Cp(35),
+        Score::Cp(-120),
+        Score::Cp(0),
+        Score::Mate(3),
+        Score::Mate(-1),
+    ] {
+        assert_eq!(score.to_string().parse::<Score>().unwrap(), score);
+    }
+    assert_eq!(Score::Cp(35).to_string(), "+0.35");
+    assert_eq!(Score::Mate(-4).to_string(), "-M4");
+    assert_eq!(-Score::Mate(4), Score::Mate(-4));
+
+    // Bounds are written as a suffix, and flip with the score
+    for bound in [ScoreBound::Exact, ScoreBound::Lower, ScoreBound::Upper] {
+        for score in [
+            Score::Cp(35),
+            Score::Cp(-120),
+            Score::Mate(3),
+            Score::Mate(-1),
+        ] {
+            let comment = format!("{}{}", score, bound);
+            assert_eq!(Score::parse_with_bound(&comment).unwrap(), (score, bound));
+            assert_eq!(comment.parse::<Score>().unwrap(), score);
+        }
+    }
+    assert_eq!(format!("{}{}", Score::Cp(35), ScoreBound::Lower), "+0.35+");
+    assert_eq!(format!("{}{}", Score::Mate(2), ScoreBound::Upper), "+M2-");
+    assert_eq!(-ScoreBound::Lower, ScoreBound::Upper);
+    assert!("+".parse::<Score>().is_err());
+    assert!("0.35++".parse::<Score>().is_err());
+
+    assert!(Score::Mate(5).to_cp() > Score::Cp(10_000).to_cp());
+    assert!(Score::Mate(2).to_cp() > Score::Mate(5).to_cp());
+    assert!(Score::Mate(-2).to_cp() < Score::Mate(-5).to_cp());
+}
@@ -1,16 +1,33 @@
+use crate::adjudication::AdjudicationSettings;
+use crate::affinity::{self, AffinitySettings};
 use crate::engine::{Engine, EngineBuilder};
-use crate::game::ScheduledGame;
-use crate::openings::Opening;
+use crate::game::{AnnotationSettings, ScheduledGame};
+use crate::knockout::{BracketEntry, Knockout, TieBreak};
+use crate::opening_report::{self, OpeningReportSettings};
+use crate::openings::{self, Opening};
 use crate::pgn_writer::PgnWriter;
-use crate::simulation::MatchScore;
+use crate::progress::{self, Progress};
+use crate::rating::{self, Ratings};
+use crate::results::{
+    self, EloReport, EngineReport, GameReport, PairReport, RatingReport, ResultsReport, SprtReport,
+    SprtStatus,
+};
+use crate::simulation::{MatchScore, ScoreEstimate};
 use crate::sprt::{PentanomialResult, SprtParameters};
+use crate::spsa::{Spsa, SpsaSettings};
+use crate::stats::{self, GameStats};
+use crate::swiss::{self, Swiss};
 use crate::{exit_with_error, simulation};
 use board_game_traits::GameResult::*;
+use log::{info, warn};
 use pgn_traits::PgnPosition;
+use std::cmp::Reverse;
+use std::io::{IsTerminal, Write};
 use std::num::NonZeroUsize;
 use std::sync::atomic::{self, AtomicBool};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
 use std::{fmt, io};
 use tiltak::ptn::Game;
 
@@ -19,7 +36,21 @@ pub enum TournamentType {
     Gauntlet(NonZeroUsize),
     RoundRobin(usize),
     BookTest(usize),
-    Sprt,
+    /// Test one or more candidate engines against the base engine, each with its own SPRT
+    Sprt(NonZeroUsize),
+    /// Tune a single engine's options against itself
+    Spsa,
+    /// Pair engines with similar scores each round, with rounds scheduled as results come in
+    Swiss {
+        num_engines: usize,
+        rounds: usize,
+    },
+    /// Single-elimination bracket, where each match is a number of game pairs
+    Knockout {
+        num_engines: usize,
+        game_pairs: usize,
+        tie_break: TieBreak,
+    },
 }
 
 impl TournamentType {
@@ -28,7 +59,30 @@ impl TournamentType {
             TournamentType::Gauntlet(num_challengers) => num_challengers.get() + 1,
             TournamentType::RoundRobin(num_engines) => num_engines,
             TournamentType::BookTest(num_engines) => num_engines,
-            TournamentType::Sprt => 2,
+            TournamentType::Sprt(num_candidates) => num_candidates.get() + 1,
+            TournamentType::Spsa => 2,
+            TournamentType::Swiss { num_engines, .. }
+            | TournamentType::Knockout { num_engines, .. } => num_engines,
+        }
+    }
+
+    /// The pairs of engines whose head-to-head results are reported.
+    /// Challengers and SPRT candidates are reported against the base engine, not the other way around
+    pub fn reported_pairs(self) -> Vec<(usize, usize)> {
+        match self {
+            TournamentType::Gauntlet(num_challengers) => {
+                (1..=num_challengers.get()).map(|id| (id, 0)).collect()
+            }
+            TournamentType::RoundRobin(num_engines)
+            | TournamentType::BookTest(num_engines)
+            | TournamentType::Swiss { num_engines, .. }
+            | TournamentType::Knockout { num_engines, .. } => (0..num_engines)
+                .flat_map(|id1| (id1 + 1..num_engines).map(move |id2| (id1, id2)))
+                .collect(),
+            TournamentType::Sprt(num_candidates) => {
+                (1..=num_candidates.get()).map(|id| (id, 0)).collect()
+            }
+            TournamentType::Spsa => vec![(0, 1)],
         }
     }
 
@@ -38,7 +92,9 @@ impl TournamentType {
             TournamentType::Gauntlet(num_challengers) => num_challengers.get() * 2,
             TournamentType::RoundRobin(num_engines) => num_engines * (num_engines - 1),
             TournamentType::BookTest(num_engines) => num_engines * num_engines,
-            TournamentType::Sprt => 2,
+            TournamentType::Sprt(num_candidates) => num_candidates.get() * 2,
+            TournamentType::Spsa | TournamentType::Knockout { .. } => 2,
+            TournamentType::Swiss { num_engines, .. } => swiss::games_per_round(num_engines),
         }
     }
 }
@@ -53,6 +109,23 @@ pub struct TournamentSettings<B: PgnPosition> {
     pub pgn_writer: Mutex<PgnWriter<B>>,
     pub tournament_type: TournamentType,
     pub sprt: Option<SprtParameters>,
+    pub spsa: Option<SpsaSettings>,
+    pub adjudication: AdjudicationSettings,
+    pub annotation: AnnotationSettings,
+    pub results_json: Option<String>,
+    /// Print the full results every this many games, and a one-line summary in between.
+    /// If not set, defaults to every 100 games when printing to a terminal, and every game otherwise
+    pub report_interval: Option<usize>,
+    /// Print the game statistics with every full report, instead of only at the end
+    pub periodic_stats: bool,
+    /// Fit white's first-move advantage along with the ratings
+    pub fit_white_advantage: bool,
+    pub opening_report: Option<OpeningReportSettings>,
+    /// The maximum number of engine processes running in each worker
+    pub max_engines_per_worker: Option<usize>,
+    pub affinity: Option<AffinitySettings>,
+    /// Games played in an earlier, interrupted run of the same tournament
+    pub resumed_games: Vec<Game<B>>,
 }
 
 impl<B: PgnPosition> fmt::Debug for TournamentSettings<B> {
@@ -64,6 +137,8 @@ impl<B: PgnPosition> fmt::Debug for TournamentSettings<B> {
 }
 
 impl<B: PgnPosition + Clone> TournamentSettings<B> {
+    /// The games that can be scheduled before the tournament starts.
+    /// For formats where later rounds depend on earlier results, this is only the first round
     pub fn schedule(&self) -> Vec<ScheduledGame<B>> {
         match self.tournament_type {
             TournamentType::Gauntlet(num_challengers) => (0..self.num_games)
@@ -115,7 +190,29 @@ impl<B: PgnPosition + Clone> TournamentSettings<B> {
                     size: self.size,
                 })
                 .collect(),
-            TournamentType::Sprt => (0..self.num_games)
+            // Each candidate plays one game pair in turn, and every candidate plays the same openings
+            TournamentType::Sprt(num_candidates) => (0..self.num_games)
+                .map(|round_number| {
+                    let pair_number = round_number / 2;
+                    let candidate_id = pair_number % num_candidates.get() + 1;
+                    let (white_id, black_id) = if round_number % 2 == 0 {
+                        (0, candidate_id)
+                    } else {
+                        (candidate_id, 0)
+                    };
+                    ScheduledGame {
+                        round_number,
+                        opening: self.openings[(self.openings_start_index
+                            + pair_number / num_candidates.get())
+                            % self.openings.len()]
+                        .clone(),
+                        white_engine_id: EngineId(white_id),
+                        black_engine_id: EngineId(black_id),
+                        size: self.size,
+                    }
+                })
+                .collect(),
+            TournamentType::Spsa => (0..self.num_games)
                 .map(|round_number| ScheduledGame {
                     round_number,
                     opening: self.openings
@@ -126,6 +223,61 @@ impl<B: PgnPosition + Clone> TournamentSettings<B> {
                     size: self.size,
                 })
                 .collect(),
+            TournamentType::Swiss { .. } | TournamentType::Knockout { .. } => {
+                self.dynamic_schedule().unwrap().next_round(&[], &[])
+            }
+        }
+    }
+
+    fn dynamic_schedule(&self) -> Option<DynamicSchedule<B>> {
+        match self.tournament_type {
+            TournamentType::Swiss {
+                num_engines,
+                rounds,
+            } => Some(DynamicSchedule::Swiss(Swiss::new(
+                num_engines,
+                rounds,
+                self.openings.clone(),
+                self.openings_start_index,
+                self.size,
+            ))),
+            TournamentType::Knockout {
+                num_engines,
+                game_pairs,
+                tie_break,
+            } => Some(DynamicSchedule::Knockout(Knockout::new(
+                num_engines,
+                game_pairs,
+                tie_break,
+                self.fit_white_advantage,
+                self.openings.clone(),
+                self.openings_start_index,
+                self.size,
+            ))),
+            _ => None,
+        }
+    }
+}
+
+/// Scheduling for formats where later rounds depend on the results of earlier rounds
+enum DynamicSchedule<B: PgnPosition> {
+    Swiss(Swiss<B>),
+    Knockout(Knockout<B>),
+}
+
+impl<B: PgnPosition + Clone> DynamicSchedule<B> {
+    /// Schedule the next round, once every scheduled game has finished.
+    /// Returns no games when the tournament is over
+    fn next_round(
+        &mut self,
+        scheduled_games: &[ScheduledGame<B>],
+        finished_games: &[Option<Game<B>>],
+    ) -> Vec<ScheduledGame<B>> {
+        match self {
+            DynamicSchedule::Swiss(swiss) => swiss.next_round(scheduled_games, finished_games),
+            DynamicSchedule::Knockout(knockout) => {
+                knockout.next_round(scheduled_games, finished_games)
+            }
         }
     }
 }
@@ -143,9 +295,31 @@ pub struct Tournament<B: PgnPosition> {
     position_settings: B::Settings,
     pub games_schedule: Mutex<GamesSchedule<B>>,
     finished_games: Mutex<Vec<Option<Game<B>>>>,
+    /// Notified when a game finishes, for workers waiting for a round to finish before the next can be scheduled
+    game_finished: Condvar,
     pgn_writer: Mutex<PgnWriter<B>>,
     tournament_type: TournamentType,
     sprt: Option<SprtParameters>,
+    /// Status of each SPRT candidate's test
+    sprt_statuses: Mutex<Vec<SprtStatus>>,
+    spsa: Option<Mutex<Spsa>>,
+    dynamic_schedule: Option<Mutex<DynamicSchedule<B>>>,
+    /// The number of games to play, or the minimum number for knockout tournaments
+    num_games: usize,
+    progress: Mutex<Progress>,
+    /// Whether stdout is a terminal, where the one-line summary is refreshed in place
+    is_terminal: bool,
+    report_interval: usize,
+    periodic_stats: bool,
+    fit_white_advantage: bool,
+    opening_report: Option<OpeningReportSettings>,
+    /// The opening book, for the opening report
+    openings: Vec<Opening<B>>,
+    max_engines_per_worker: Option<usize>,
+    affinity: Option<AffinitySettings>,
+    adjudication: AdjudicationSettings,
+    annotation: AnnotationSettings,
+    results_json: Option<String>,
 }
 
 impl<B> Tournament<B>
@@ -155,7 +329,27 @@ where
     B::Settings: Send + Sync,
 {
     pub fn new(settings: TournamentSettings<B>) -> Self {
-        let scheduled_games = settings.schedule();
+        let mut dynamic_schedule = settings.dynamic_schedule();
+        let scheduled_games = match dynamic_schedule.as_mut() {
+            Some(dynamic_schedule) => dynamic_schedule.next_round(&[], &[]),
+            None => settings.schedule(),
+        };
+        let finished_games = restore_games(&scheduled_games, settings.resumed_games)
+            .unwrap_or_else(|err| exit_with_error(&format!("Cannot resume tournament: {}", err)));
+
+        let is_terminal = io::stdout().is_terminal();
+
+        // Each game pair is one SPSA iteration
+        let num_spsa_iterations = settings.num_games / 2;
+
+        let pgn_writer = settings.pgn_writer;
+        pgn_writer.lock().unwrap().skip_games(
+            finished_games
+                .iter()
+                .enumerate()
+                .filter(|(_, game)| game.is_some())
+                .map(|(round_number, _)| round_number),
+        );
 
         Tournament {
             position_settings: settings.position_settings,
@@ -163,39 +357,74 @@ where
                 scheduled_games,
                 next_game_id: 0,
             }),
-            finished_games: Mutex::new(vec![None; settings.num_games]),
-            pgn_writer: settings.pgn_writer,
+            finished_games: Mutex::new(finished_games),
+            game_finished: Condvar::new(),
+            pgn_writer,
             tournament_type: settings.tournament_type,
             sprt: settings.sprt,
+            sprt_statuses: Mutex::new(match settings.tournament_type {
+                TournamentType::Sprt(num_candidates) => {
+                    vec![SprtStatus::Running; num_candidates.get()]
+                }
+                _ => vec![],
+            }),
+            spsa: settings
+                .spsa
+                .map(|spsa| Mutex::new(Spsa::new(spsa, num_spsa_iterations))),
+            dynamic_schedule: dynamic_schedule.map(Mutex::new),
+            num_games: settings.num_games,
+            progress: Mutex::new(Progress::new(settings.concurrency)),
+            is_terminal,
+            report_interval: settings
+                .report_interval
+                .unwrap_or(if is_terminal { 100 } else { 1 }),
+            periodic_stats: settings.periodic_stats,
+            fit_white_advantage: settings.fit_white_advantage,
+            opening_report: settings.opening_report,
+            openings: settings.openings,
+            max_engines_per_worker: settings.max_engines_per_worker,
+            affinity: settings.affinity,
+            adjudication: settings.adjudication,
+            annotation: settings.annotation,
+            results_json: settings.results_json,
         }
     }
 
     pub fn initialize_with_options_or_exit(builder: &EngineBuilder) -> Engine {
-        let mut engine = match builder.init() {
-            Ok(engine) => engine,
-            Err(err) => match err.kind() {
-                io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => {
-                    exit_with_error(&format!(
-                        "Failed to start engine \"{}\", caused by: {}",
-                        builder.path, err
-                    ))
-                }
-                _ => exit_with_error(&format!(
+        Self::initialize_with_options(builder)
+            .unwrap_or_else(|err| exit_with_error(&err.to_string()))
+    }
+
+    /// Start the engine and set its options, with an error message that names the engine
+    fn initialize_with_options(builder: &EngineBuilder) -> io::Result<Engine> {
+        let mut engine = builder.init().map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => io::Error::new(
+                err.kind(),
+                format!(
+                    "Failed to start engine \"{}\", caused by: {}",
+                    builder.path, err
+                ),
+            ),
+            _ => io::Error::new(
+                err.kind(),
+                format!(
                     "Error while initializing \"{}\", the engine may have crashed. Caused by: {}",
                     builder.path, err
-                )),
-            },
-        };
+                ),
+            ),
+        })?;
 
-        if engine.supports_options_from_builder() {
-            engine.set_options_from_builder().unwrap();
-        } else {
-            exit_with_error(&format!(
-                "Engine \"{}\" does not support given options",
-                engine.name(),
+        if !engine.supports_options_from_builder() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Engine \"{}\" does not support given options",
+                    engine.name()
+                ),
             ));
         }
-        engine
+        engine.set_options_from_builder()?;
+        Ok(engine)
     }
 
     pub fn play(
@@ -204,35 +433,141 @@ where
         is_shutting_down: &'static AtomicBool,
         engine_builders: &[EngineBuilder],
     ) {
-        let engine_names: Vec<String> = engine_builders
+        // Start each engine once, to check that it starts and to get its name.
+        // These engines are kept by the first worker, up to its maximum number of engines,
+        // and the other workers start engines when a game needs them
+        println!("Initializing engines");
+
+        let num_engines = engine_builders.len();
+        let engine_core = |worker_id: usize, engine_id: usize| {
+            self.affinity
+                .as_ref()
+                .map(|affinity| affinity.engine_core(worker_id, engine_id, num_engines))
+        };
+        // The engines' names aren't known until they have started, so the stderr files are named after the given names
+        let stderr_file_names = unique_names(
+            engine_builders
+                .iter()
+                .map(|builder| builder.name.clone().unwrap_or_else(|| builder.path.clone()))
+                .collect(),
+        );
+        let engine_builders: Vec<EngineBuilder> = engine_builders
             .iter()
-            .map(|builder| builder.path.clone())
+            .zip(stderr_file_names)
+            .map(|(builder, stderr_file_name)| EngineBuilder {
+                stderr_file_name: Some(stderr_file_name),
+                ..builder.clone()
+            })
             .collect();
 
-        // Initialize engines
-        println!("Initializing engines");
+        let mut first_engines: Vec<Engine> = engine_builders
+            .iter()
+            .enumerate()
+            .map(|(engine_id, builder)| {
+                Self::initialize_with_options_or_exit(&EngineBuilder {
+                    worker_id: Some(0),
+                    cpu_core: engine_core(0, engine_id),
+                    ..builder.clone()
+                })
+            })
+            .collect();
 
-        let workers: Vec<Worker> = (0..threads)
-            .map(|id| Worker {
-                id,
-                engines: engine_builders
-                    .iter()
-                    .map(|builder| Self::initialize_with_options_or_exit(builder))
-                    .collect(),
+        if let Some(spsa) = self.spsa.as_ref() {
+            spsa.lock()
+                .unwrap()
+                .init(&first_engines[0])
+                .unwrap_or_else(|err| exit_with_error(&err));
+        }
+
+        let engine_names = unique_names(
+            first_engines
+                .iter()
+                .map(|engine| engine.name().to_string())
+                .collect(),
+        );
+        for (engine, name) in first_engines.iter_mut().zip(engine_names.iter()) {
+            engine.set_name(name);
+        }
+        for engine in first_engines.iter() {
+            match engine.author() {
+                Some(author) => println!("{} by {}", engine.name(), author),
+                None => println!("{}", engine.name()),
+            }
+        }
+
+        let mut workers: Vec<Worker> = (0..threads)
+            .map(|id| {
+                Worker::new(
+                    id,
+                    engine_builders
+                        .iter()
+                        .zip(engine_names.iter())
+                        .enumerate()
+                        .map(|(engine_id, (builder, name))| EngineBuilder {
+                            worker_id: Some(id),
+                            name: Some(name.clone()),
+                            cpu_core: engine_core(id, engine_id),
+                            ..builder.clone()
+                        })
+                        .collect(),
+                )
             })
             .collect();
+        let max_engines = self.max_engines_per_worker.unwrap_or(usize::MAX);
+        workers[0].engines = first_engines
+            .into_iter()
+            .enumerate()
+            .map(|(engine_id, mut engine)| {
+                if engine_id < max_engines {
+                    return Some(engine);
+                }
+                if let Err(err) = engine.shutdown() {
+                    warn!(
+                        "Failed to shut down engine {} in worker #0: {}",
+                        engine.name(),
+                        err
+                    );
+                }
+                None
+            })
+            .collect();
+
+        if self.affinity.is_some() {
+            for worker in workers.iter() {
+                let assignment: Vec<String> = worker
+                    .builders
+                    .iter()
+                    .zip(engine_names.iter())
+                    .map(|(builder, name)| {
+                        format!("{} on core {}", name, builder.cpu_core.unwrap())
+                    })
+                    .collect();
+                println!("Worker #{}: {}", worker.id, assignment.join(", "));
+                info!("Worker #{}: {}", worker.id, assignment.join(", "));
+            }
+        }
 
         let tournament_arc = Arc::new(self);
 
+        let num_resumed_games = tournament_arc
+            .finished_games
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .count();
+        if num_resumed_games > 0 {
+            println!(
+                "Resuming tournament, {} games were already played",
+                num_resumed_games
+            );
+            tournament_arc.print_score(&engine_names, is_shutting_down, true);
+        }
+
         println!(
             "Starting {} worker thread(s) to play {} games",
             workers.len(),
-            tournament_arc
-                .games_schedule
-                .lock()
-                .unwrap()
-                .scheduled_games
-                .len()
+            tournament_arc.num_games - num_resumed_games
         );
 
         let thread_handles: Vec<JoinHandle<()>> = workers
@@ -243,14 +578,43 @@ where
                 Builder::new()
                     .name(format!("#{}", worker.id)) // Note: The threads' names are used for logging
                     .spawn(move || {
-                        while let Some(scheduled_game) = thread_tournament.next_unplayed_game() {
+                        if let Some(settings) = thread_tournament.affinity.as_ref() {
+                            let cores = settings.worker_cores(worker.id, worker.builders.len());
+                            if let Err(err) = affinity::set_thread_affinity(&cores) {
+                                exit_with_error(&format!(
+                                    "Failed to pin worker #{} to cores {:?}: {}",
+                                    worker.id, cores, err
+                                ));
+                            }
+                        }
+                        while let Some(scheduled_game) =
+                            thread_tournament.next_unplayed_game(is_shutting_down)
+                        {
                             if is_shutting_down.load(atomic::Ordering::SeqCst) {
                                 break;
                             }
                             let round_number = scheduled_game.round_number;
-                            let game = match scheduled_game
-                                .play_game(&mut worker, &thread_tournament.position_settings)
-                            {
+                            let start_time = Instant::now();
+                            let first_engine_is_white = scheduled_game.white_engine_id.0 == 0;
+                            let game = match thread_tournament
+                                .start_engines(
+                                    &mut worker,
+                                    &[
+                                        scheduled_game.white_engine_id.0,
+                                        scheduled_game.black_engine_id.0,
+                                    ],
+                                )
+                                .and_then(|()| {
+                                    thread_tournament.set_spsa_options(&mut worker, round_number)
+                                })
+                                .and_then(|()| {
+                                    scheduled_game.play_game(
+                                        &mut worker,
+                                        &thread_tournament.position_settings,
+                                        &thread_tournament.adjudication,
+                                        &thread_tournament.annotation,
+                                    )
+                                }) {
                                 Ok(game) => game,
                                 // If an error occurs that wasn't handled in play_game(), soft-abort the match
                                 // and write a dummy game to the pgn output, so that later games won't be held up
@@ -283,13 +647,38 @@ where
                                     thread_tournament.finished_games.lock().unwrap();
                                 finished_games[round_number] = Some(game.clone());
                             }
+                            {
+                                let _games_schedule =
+                                    thread_tournament.games_schedule.lock().unwrap();
+                                thread_tournament.game_finished.notify_all();
+                            }
+                            if let (Some(spsa), Some(result)) =
+                                (thread_tournament.spsa.as_ref(), game.game_result())
+                            {
+                                if let Err(err) = spsa.lock().unwrap().record_game(
+                                    round_number / 2,
+                                    first_engine_is_white,
+                                    result,
+                                ) {
+                                    log::error!("Failed to write to spsa log: {}", err);
+                                }
+                            }
                             {
                                 let writer = &thread_tournament.pgn_writer;
                                 writer.lock().unwrap().submit_game(round_number, game);
                             }
-                            thread_tournament.print_score(&engine_names, is_shutting_down);
+                            let games_finished = thread_tournament
+                                .progress
+                                .lock()
+                                .unwrap()
+                                .record_game(start_time.elapsed());
+                            thread_tournament.print_score(
+                                &engine_names,
+                                is_shutting_down,
+                                games_finished % thread_tournament.report_interval == 0,
+                            );
                         }
-                        for engine in worker.engines.iter_mut() {
+                        for engine in worker.engines.iter_mut().flatten() {
                             engine.shutdown().unwrap();
                         }
                     })
@@ -299,10 +688,74 @@ where
         for thread_handle in thread_handles {
             thread_handle.join().unwrap();
         }
-        tournament_arc.print_score(&engine_names, is_shutting_down);
+        tournament_arc.print_score(&engine_names, is_shutting_down, true);
+        if !tournament_arc.periodic_stats {
+            tournament_arc.print_stats(&engine_names);
+        }
+        tournament_arc.finish_opening_report();
+    }
+
+    fn print_stats(&self, engine_names: &[String]) {
+        let schedule = self.games_schedule.lock().unwrap();
+        let finished_games = self.finished_games.lock().unwrap();
+        GameStats::new(
+            self.tournament_type.num_engines(),
+            &schedule.scheduled_games,
+            &finished_games,
+        )
+        .print(engine_names);
+    }
+
+    /// Print the opening report and write the filtered book, if requested
+    fn finish_opening_report(&self) {
+        let Some(settings) = self.opening_report.as_ref() else {
+            return;
+        };
+        let schedule = self.games_schedule.lock().unwrap();
+        let finished_games = self.finished_games.lock().unwrap();
+        let results = opening_report::opening_results(
+            &self.openings,
+            &schedule.scheduled_games,
+            &finished_games,
+        );
+        if settings.print {
+            opening_report::print_opening_results(&self.openings, &results);
+        }
+        if let Some(path) = settings.filtered_book.as_ref() {
+            let lopsided: Vec<usize> = results
+                .iter()
+                .filter(|result| result.is_lopsided())
+                .map(|result| result.index)
+                .collect();
+            let filtered_openings: Vec<Opening<B>> = self
+                .openings
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !lopsided.contains(index))
+                .map(|(_, opening)| opening.clone())
+                .collect();
+            match openings::write_openings(path, &filtered_openings, settings.book_format) {
+                Ok(()) => println!(
+                    "Wrote {} openings to {}, removed {} lopsided openings",
+                    filtered_openings.len(),
+                    path,
+                    lopsided.len()
+                ),
+                Err(err) => {
+                    println!("Failed to write filtered book to {}: {}", path, err);
+                    log::error!("Failed to write filtered book to {}: {}", path, err);
+                }
+            }
+        }
     }
 
-    fn print_score(&self, engine_names: &[String], is_shutting_down: &'static AtomicBool) {
+    /// Update the results, and print either the full results or a one-line summary
+    fn print_score(
+        &self,
+        engine_names: &[String],
+        is_shutting_down: &'static AtomicBool,
+        full_report: bool,
+    ) {
         let (schedule, finished_games) = loop {
             if let Ok(schedule) = self.games_schedule.try_lock() {
                 if let Ok(finished_games) = self.finished_games.try_lock() {
@@ -319,6 +772,12 @@ where
             vec![vec![0; self.tournament_type.num_engines()]; self.tournament_type.num_engines()];
         let mut engine_losses: Vec<Vec<u64>> =
             vec![vec![0; self.tournament_type.num_engines()]; self.tournament_type.num_engines()];
+        // Each engine's score with white against each other engine
+        let mut color_results: Vec<Vec<MatchScore>> =
+            vec![
+                vec![MatchScore::default(); self.tournament_type.num_engines()];
+                self.tournament_type.num_engines()
+            ];
 
         let mut white_wins = 0;
         let mut black_wins = 0;
@@ -330,8 +789,11 @@ where
             .zip(finished_games.iter())
             .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
         {
+            let white_score = &mut color_results[scheduled_game.white_engine_id.0]
+                [scheduled_game.black_engine_id.0];
             match game.game_result() {
                 Some(WhiteWin) => {
+                    white_score.wins += 1;
                     engine_wins[scheduled_game.white_engine_id.0]
                         [scheduled_game.black_engine_id.0] += 1;
                     engine_losses[scheduled_game.black_engine_id.0]
@@ -339,6 +801,7 @@ where
                     white_wins += 1;
                 }
                 Some(BlackWin) => {
+                    white_score.losses += 1;
                     engine_wins[scheduled_game.black_engine_id.0]
                         [scheduled_game.white_engine_id.0] += 1;
                     engine_losses[scheduled_game.white_engine_id.0]
@@ -347,6 +810,7 @@ where
                 }
 
                 None | Some(Draw) => {
+                    white_score.draws += 1;
                     engine_draws[scheduled_game.white_engine_id.0]
                         [scheduled_game.black_engine_id.0] += 1;
                     engine_draws[scheduled_game.black_engine_id.0]
@@ -356,14 +820,6 @@ where
             }
         }
 
-        println!(
-            "Played {} games. {} white wins, {} black wins, {} draws.",
-            finished_games.iter().filter(|a| a.is_some()).count(),
-            white_wins,
-            black_wins,
-            draws
-        );
-
         assert_eq!(
             engine_wins.iter().flatten().sum::<u64>() + draws,
             finished_games.iter().flatten().count() as u64
@@ -379,196 +835,852 @@ where
 
         assert_eq!(draws, engine_draws.iter().flatten().sum::<u64>() / 2);
 
-        match self.tournament_type {
-            TournamentType::RoundRobin(2) => {
-                print_head_to_head_score(&engine_wins, &engine_draws, engine_names, 0, 1)
+        // Ratings are only shown for formats with more than two engines, once every engine has played
+        let num_engines = self.tournament_type.num_engines();
+        let every_engine_has_played = (0..num_engines).all(|id| {
+            (0..num_engines)
+                .filter(|other_id| *other_id != id)
+                .any(|other_id| {
+                    color_results[id][other_id].num_games()
+                        + color_results[other_id][id].num_games()
+                        > 0
+                })
+        });
+        // Head-to-head results are only printed for some formats, but always written to the json output
+        let pair_results: Vec<PairResult> = match self.tournament_type {
+            TournamentType::RoundRobin(3..)
+            | TournamentType::BookTest(_)
+            | TournamentType::Swiss { .. }
+            | TournamentType::Knockout { .. }
+                if self.results_json.is_none() =>
+            {
+                vec![]
             }
-            // For gauntlet tournament, prints the challengers' scores vs the champion,
-            // instead of the other way around
-            TournamentType::Gauntlet(num_challengers) => {
-                for engine2_id in 1..=num_challengers.get() {
-                    print_head_to_head_score(
-                        &engine_wins,
-                        &engine_draws,
-                        engine_names,
+            _ => self
+                .tournament_type
+                .reported_pairs()
+                .into_iter()
+                .map(|(engine1_id, engine2_id)| {
+                    let score = MatchScore {
+                        wins: engine_wins[engine1_id][engine2_id],
+                        draws: engine_draws[engine1_id][engine2_id],
+                        losses: engine_wins[engine2_id][engine1_id],
+                    };
+                    PairResult {
+                        engine1_id,
                         engine2_id,
-                        0,
-                    )
-                }
+                        score,
+                        pentanomial: pair_penta_stats(
+                            &schedule.scheduled_games,
+                            &finished_games,
+                            engine1_id,
+                            engine2_id,
+                        ),
+                        estimate: ScoreEstimate::normal_approximation(score),
+                    }
+                })
+                .collect(),
+        };
+
+        let sprt_reports: Vec<SprtReport> = match (self.tournament_type, self.sprt) {
+            (TournamentType::Sprt(_), Some(sprt)) => {
+                let (elo0, elo1) = sprt.elo_bounds();
+                let (lower_bound, upper_bound) = sprt.llr_bounds();
+                let mut sprt_statuses = self.sprt_statuses.lock().unwrap();
+                pair_results
+                    .iter()
+                    .map(|pair_result| {
+                        let llr = sprt.llr(pair_result.pentanomial);
+                        // A candidate's test is over once it reaches a bound,
+                        // even if games that were already running move the llr back
+                        let status = &mut sprt_statuses[pair_result.engine1_id - 1];
+                        if *status == SprtStatus::Running {
+                            if llr <= lower_bound {
+                                *status = SprtStatus::Failed;
+                            } else if llr >= upper_bound {
+                                *status = SprtStatus::Passed;
+                            }
+                        }
+                        SprtReport {
+                            base: engine_names[0].clone(),
+                            under_test: engine_names[pair_result.engine1_id].clone(),
+                            elo0,
+                            elo1,
+                            llr,
+                            lower_bound,
+                            upper_bound,
+                            status: *status,
+                        }
+                    })
+                    .collect()
             }
-            TournamentType::RoundRobin(num_engines)
-            | TournamentType::BookTest(num_engines @ 2..) => {
-                println!(
-                    "{:16} {:>4} {:>4} {:>4} {:>7}",
-                    "Name", "+", "-", "=", "Score"
-                );
-                for id in 0..num_engines {
-                    // The engine's results against every engine except itself:
-                    let num_wins = engine_wins[id].iter().sum::<u64>() - engine_wins[id][id];
-                    let num_draws: u64 =
-                        engine_draws[id].iter().sum::<u64>() - engine_draws[id][id];
-                    let num_losses: u64 =
-                        engine_losses[id].iter().sum::<u64>() - engine_losses[id][id];
-                    let num_games = num_wins + num_draws + num_losses;
+            _ => vec![],
+        };
+
+        // Games of candidates that have finished their test are skipped,
+        // and the run ends once every candidate has finished
+        if !sprt_reports.is_empty()
+            && sprt_reports
+                .iter()
+                .all(|sprt_report| sprt_report.status != SprtStatus::Running)
+        {
+            is_shutting_down.store(true, atomic::Ordering::SeqCst);
+        }
+
+        // Gather everything else that needs the games, and release the locks before the slower work
+        let games_played = finished_games.iter().flatten().count();
+        let swiss_standings = match self.tournament_type {
+            TournamentType::Swiss { num_engines, .. } if full_report => {
+                let games_per_round = swiss::games_per_round(num_engines);
+                let rounds_finished = finished_games
+                    .chunks(games_per_round)
+                    .take_while(|round| round.iter().all(Option::is_some))
+                    .count();
+                Some((
+                    swiss::scores(num_engines, &schedule.scheduled_games, &finished_games),
+                    rounds_finished,
+                ))
+            }
+            _ => None,
+        };
+        let bracket = match self.dynamic_schedule.as_ref() {
+            Some(dynamic_schedule) if full_report => match &*dynamic_schedule.lock().unwrap() {
+                DynamicSchedule::Knockout(knockout) => Some(format_bracket(
+                    knockout,
+                    &schedule.scheduled_games,
+                    &finished_games,
+                    engine_names,
+                )),
+                _ => None,
+            },
+            _ => None,
+        };
+        let game_stats = if full_report && self.periodic_stats {
+            Some(GameStats::new(
+                self.tournament_type.num_engines(),
+                &schedule.scheduled_games,
+                &finished_games,
+            ))
+        } else {
+            None
+        };
+        let summary_line = if full_report {
+            None
+        } else {
+            Some(status_line(
+                &self.progress.lock().unwrap(),
+                games_played,
+                self.num_games,
+                &finished_games,
+                &pair_results,
+                &sprt_reports,
+            ))
+        };
+        let game_reports: Vec<GameReport> = if self.results_json.is_some() {
+            schedule
+                .scheduled_games
+                .iter()
+                .zip(finished_games.iter())
+                .map(|(scheduled_game, game)| GameReport {
+                    round: scheduled_game.round_number + 1,
+                    white: engine_names[scheduled_game.white_engine_id.0].clone(),
+                    black: engine_names[scheduled_game.black_engine_id.0].clone(),
+                    opening_tps: scheduled_game.opening.root_position.to_fen(),
+                    opening_moves: scheduled_game.opening.moves_string(),
+                    result: game
+                        .as_ref()
+                        .and_then(|game| game.game_result_str)
+                        .map(ToString::to_string),
+                    termination: game.as_ref().and_then(|game| {
+                        game.tags
+                            .iter()
+                            .find(|(tag, _)| tag == "Termination")
+                            .map(|(_, value)| value.clone())
+                    }),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        drop(schedule);
+        drop(finished_games);
+
+        // Fitting the ratings is expensive, so skip it for the status line printed after every game
+        let ratings = match self.tournament_type {
+            TournamentType::RoundRobin(_)
+            | TournamentType::BookTest(_)
+            | TournamentType::Gauntlet(_)
+            | TournamentType::Swiss { .. }
+            | TournamentType::Knockout { .. }
+                if num_engines >= 3
+                    && every_engine_has_played
+                    && (full_report || self.results_json.is_some()) =>
+            {
+                Some(rating::fit_ratings(
+                    &color_results,
+                    self.fit_white_advantage,
+                ))
+            }
+            _ => None,
+        };
 
+        if full_report {
+            if self.is_terminal {
+                // Clear the status line
+                print!("\r\x1b[2K");
+            }
+            println!(
+                "Played {} games. {} white wins, {} black wins, {} draws.",
+                games_played, white_wins, black_wins, draws
+            );
+
+            match self.tournament_type {
+                // For gauntlet tournament, prints the challengers' scores vs the champion,
+                // instead of the other way around
+                TournamentType::RoundRobin(2) | TournamentType::Gauntlet(_) => {
+                    for pair_result in pair_results.iter() {
+                        print_head_to_head_score(pair_result, engine_names)
+                    }
+                }
+                TournamentType::RoundRobin(num_engines)
+                | TournamentType::BookTest(num_engines @ 2..) => {
                     println!(
-                        "{:16} {:4} {:4} {:4} {:>6.1}%",
-                        engine_names[id],
-                        num_wins,
-                        num_losses,
-                        num_draws,
-                        100.0 * (num_wins as f32 + num_draws as f32 / 2.0) / num_games as f32
+                        "{:16} {:>4} {:>4} {:>4} {:>7}",
+                        "Name", "+", "-", "=", "Score"
                     );
+                    for id in 0..num_engines {
+                        // The engine's results against every engine except itself:
+                        let num_wins = engine_wins[id].iter().sum::<u64>() - engine_wins[id][id];
+                        let num_draws: u64 =
+                            engine_draws[id].iter().sum::<u64>() - engine_draws[id][id];
+                        let num_losses: u64 =
+                            engine_losses[id].iter().sum::<u64>() - engine_losses[id][id];
+                        let num_games = num_wins + num_draws + num_losses;
+
+                        println!(
+                            "{:16} {:4} {:4} {:4} {:>6.1}%",
+                            engine_names[id],
+                            num_wins,
+                            num_losses,
+                            num_draws,
+                            100.0 * (num_wins as f32 + num_draws as f32 / 2.0) / num_games as f32
+                        );
+                    }
                 }
-            }
-            TournamentType::BookTest(_) => (),
-            TournamentType::Sprt => {
-                println!("Base engine : {}", engine_names[0]);
-                println!("Under test  : {}", engine_names[1]);
-
-                let score = MatchScore {
-                    wins: engine_wins[1][0],
-                    draws: engine_draws[1][0],
-                    losses: engine_losses[1][0],
-                };
-                let full_simulation = simulation::FullWinstonSimulation::run_simulation(score);
-                let lower = full_simulation.result_for_p(0.025);
-                let expected = score.score();
-                let upper = full_simulation.result_for_p(0.975);
-                let lower_elo = simulation::to_elo_string(lower);
-                let expected_elo = simulation::to_elo_string(expected);
-                let upper_elo = simulation::to_elo_string(upper);
-                println!(
-                    "Elo         : {} [{}, {}] (95%)",
-                    expected_elo, lower_elo, upper_elo
-                );
-                println!(
-                    "WDL         : W: {}, D: {}, L: {}",
-                    score.wins, score.draws, score.losses
-                );
-
-                let penta = Self::sprt_penta_stats(&finished_games);
-                println!(
-                    "Penta(0-2)  : {}, {}, {}, {}, {}",
-                    penta.ll,
-                    penta.dl,
-                    penta.dd + penta.wl,
-                    penta.wd,
-                    penta.ww
-                );
-
-                if let Some(sprt) = self.sprt {
-                    let (elo0, elo1) = sprt.elo_bounds();
-                    let (lower_bound, upper_bound) = sprt.llr_bounds();
-                    let llr = sprt.llr(penta);
-
-                    let meet = if llr <= lower_bound {
-                        format!("(<= {:.2})", lower_bound)
-                    } else if llr >= upper_bound {
-                        format!("(>= {:.2})", upper_bound)
-                    } else {
-                        "".to_string()
-                    };
+                TournamentType::BookTest(_) => (),
+                TournamentType::Swiss {
+                    num_engines,
+                    rounds,
+                } => {
+                    let (scores, rounds_finished) = swiss_standings.unwrap();
+                    println!("Finished round {}/{}", rounds_finished, rounds);
                     println!(
-                        "LLR         : {:.2} {:10} [{:.2} {:.2}]",
-                        llr, meet, elo0, elo1
+                        "{:>4} {:16} {:>6} {:>4} {:>4} {:>4}",
+                        "Rank", "Name", "Points", "+", "-", "="
                     );
-
-                    if llr <= lower_bound || llr >= upper_bound {
-                        is_shutting_down.store(true, atomic::Ordering::SeqCst);
+                    let mut ranking: Vec<usize> = (0..num_engines).collect();
+                    ranking.sort_by_key(|id| Reverse(scores[*id]));
+                    for (rank, id) in ranking.into_iter().enumerate() {
+                        println!(
+                            "{:4} {:16} {:>6.1} {:4} {:4} {:4}",
+                            rank + 1,
+                            engine_names[id],
+                            scores[id] as f32 / 2.0,
+                            engine_wins[id].iter().sum::<u64>(),
+                            engine_losses[id].iter().sum::<u64>(),
+                            engine_draws[id].iter().sum::<u64>(),
+                        );
+                    }
+                }
+                TournamentType::Knockout { .. } => {
+                    if let Some(bracket) = bracket {
+                        print!("{}", bracket);
                     }
+                }
+                TournamentType::Spsa => {
+                    print_head_to_head_score(&pair_results[0], engine_names);
+                    if let Some(spsa) = self.spsa.as_ref() {
+                        let spsa = spsa.lock().unwrap();
+                        println!(
+                            "SPSA iteration {}/{}",
+                            spsa.iterations_finished(),
+                            spsa.num_iterations()
+                        );
+                        for parameter in spsa.parameters() {
+                            println!(
+                                "{:16} {:>10.2} [{}, {}]",
+                                parameter.name, parameter.value, parameter.min, parameter.max
+                            );
+                        }
+                    }
+                }
+                TournamentType::Sprt(_) => {
+                    println!("Base engine : {}", engine_names[0]);
+
+                    for (i, pair_result) in pair_results.iter().enumerate() {
+                        println!("Under test  : {}", engine_names[pair_result.engine1_id]);
+
+                        let PairResult {
+                            score,
+                            pentanomial: penta,
+                            ..
+                        } = *pair_result;
+                        let estimate = ScoreEstimate::new(score);
+                        let lower_elo = simulation::to_elo_string(estimate.lower);
+                        let expected_elo = simulation::to_elo_string(estimate.expected);
+                        let upper_elo = simulation::to_elo_string(estimate.upper);
+                        println!(
+                            "Elo         : {} [{}, {}] (95%)",
+                            expected_elo, lower_elo, upper_elo
+                        );
+                        println!(
+                            "WDL         : W: {}, D: {}, L: {}",
+                            score.wins, score.draws, score.losses
+                        );
 
-                    if llr <= lower_bound {
-                        println!("SPRT failed");
+                        println!(
+                            "Penta(0-2)  : {}, {}, {}, {}, {}",
+                            penta.ll,
+                            penta.dl,
+                            penta.dd + penta.wl,
+                            penta.wd,
+                            penta.ww
+                        );
+
+                        if let Some(sprt_report) = sprt_reports.get(i) {
+                            let meet = match sprt_report.status {
+                                SprtStatus::Failed => {
+                                    format!("(<= {:.2})", sprt_report.lower_bound)
+                                }
+                                SprtStatus::Passed => {
+                                    format!("(>= {:.2})", sprt_report.upper_bound)
+                                }
+                                SprtStatus::Running => "".to_string(),
+                            };
+                            println!(
+                                "LLR         : {:.2} {:10} [{:.2} {:.2}]",
+                                sprt_report.llr, meet, sprt_report.elo0, sprt_report.elo1
+                            );
+
+                            match sprt_report.status {
+                                SprtStatus::Failed => println!("SPRT failed"),
+                                SprtStatus::Passed => println!("SPRT passed"),
+                                SprtStatus::Running => (),
+                            }
+                        }
                     }
-                    if llr >= upper_bound {
-                        println!("SPRT passed");
+                }
+            }
+
+            if let Some(ratings) = ratings.as_ref() {
+                print_ratings(ratings, engine_names);
+            }
+
+            if let Some(game_stats) = game_stats {
+                game_stats.print(engine_names);
+            }
+        } else if let Some(line) = summary_line {
+            if self.is_terminal {
+                // Overwrite the previous status line
+                print!("\r\x1b[2K{}", line);
+                io::stdout().flush().unwrap();
+            } else {
+                println!("{}", line);
+            }
+        }
+
+        if let Some(path) = self.results_json.as_ref() {
+            let report = ResultsReport {
+                engines: engine_names
+                    .iter()
+                    .enumerate()
+                    .map(|(id, name)| EngineReport {
+                        name: name.clone(),
+                        // The engine's results against every engine except itself
+                        score: MatchScore {
+                            wins: engine_wins[id].iter().sum::<u64>() - engine_wins[id][id],
+                            draws: engine_draws[id].iter().sum::<u64>() - engine_draws[id][id],
+                            losses: engine_losses[id].iter().sum::<u64>() - engine_losses[id][id],
+                        },
+                        rating: ratings.as_ref().map(|ratings| RatingReport {
+                            elo: ratings.elos[id],
+                            error: ratings.error_bar(id),
+                        }),
+                    })
+                    .collect(),
+                games_played,
+                white_wins,
+                black_wins,
+                draws,
+                games: game_reports,
+                pairs: pair_results
+                    .iter()
+                    .map(|pair_result| PairReport {
+                        engine: engine_names[pair_result.engine1_id].clone(),
+                        opponent: engine_names[pair_result.engine2_id].clone(),
+                        score: pair_result.score,
+                        pentanomial: pair_result.pentanomial,
+                        elo: EloReport::from(pair_result.estimate),
+                    })
+                    .collect(),
+                sprt: sprt_reports,
+            };
+            if let Err(err) = results::write_json(path, &report) {
+                println!("Failed to write results to {}: {}", path, err);
+                log::error!("Failed to write results to {}: {}", path, err);
+            }
+        }
+    }
+
+    /// For SPSA tuning, set the perturbed options for the game's pair.
+    /// The first engine plays with the positive perturbation, and the second with the negative
+    fn set_spsa_options(&self, worker: &mut Worker, round_number: usize) -> io::Result<()> {
+        if let Some(spsa) = self.spsa.as_ref() {
+            let perturbation = spsa.lock().unwrap().perturbation(round_number / 2);
+            for (engine_id, values) in [&perturbation.plus, &perturbation.minus].iter().enumerate()
+            {
+                let engine = worker.engine(engine_id);
+                for (name, value) in values.iter() {
+                    engine.set_option(name, &value.to_string())?;
+                }
+                engine.do_isready_sync()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start the game's engines in the worker, if they aren't already running. If the worker is at its limit
+    /// of engine processes, the engines that have been idle the longest are shut down first
+    /// Start the engines for a game, if they are not already running in the worker.
+    /// An engine that fails to start is an error, which aborts the tournament like other fatal errors in a game
+    fn start_engines(&self, worker: &mut Worker, engine_ids: &[usize]) -> io::Result<()> {
+        worker.games_started += 1;
+        for id in engine_ids {
+            worker.last_used[*id] = worker.games_started;
+        }
+        for id in engine_ids {
+            if worker.engines[*id].is_some() {
+                continue;
+            }
+            if let Some(max_engines) = self.max_engines_per_worker {
+                while worker.engines.iter().flatten().count() >= max_engines {
+                    let Some(idle_id) = (0..worker.engines.len())
+                        .filter(|other_id| {
+                            worker.engines[*other_id].is_some() && !engine_ids.contains(other_id)
+                        })
+                        .min_by_key(|other_id| worker.last_used[*other_id])
+                    else {
+                        break;
+                    };
+                    let mut engine = worker.engines[idle_id].take().unwrap();
+                    if let Err(err) = engine.shutdown() {
+                        warn!(
+                            "Failed to shut down idle engine {} in worker #{}: {}",
+                            engine.name(),
+                            worker.id,
+                            err
+                        );
                     }
                 }
             }
+            worker.engines[*id] = Some(Self::initialize_with_options(&worker.builders[*id])?);
         }
+        Ok(())
     }
 
-    fn sprt_penta_stats(finished_games: &Vec<Option<Game<B>>>) -> PentanomialResult {
-        let mut result = PentanomialResult {
-            ww: 0,
-            wd: 0,
-            wl: 0,
-            dd: 0,
-            dl: 0,
-            ll: 0,
-        };
-        for game_pair in finished_games
-            .chunks(2)
-            .filter(|p| p.iter().all(|g| g.is_some()))
-        {
-            let result1 = game_pair[0].clone().unwrap().game_result().unwrap_or(Draw);
-            let result2 = game_pair[1].clone().unwrap().game_result().unwrap_or(Draw);
-            match (result1, result2) {
-                (WhiteWin, BlackWin) => result.ll += 1,
-                (WhiteWin, Draw) => result.dl += 1,
-                (Draw, BlackWin) => result.dl += 1,
-                (WhiteWin, WhiteWin) => result.wl += 1,
-                (BlackWin, BlackWin) => result.wl += 1,
-                (Draw, Draw) => result.dd += 1,
-                (BlackWin, Draw) => result.wd += 1,
-                (Draw, WhiteWin) => result.wd += 1,
-                (BlackWin, WhiteWin) => result.ww += 1,
+    /// Whether the game's SPRT candidate has already passed or failed its test
+    fn is_finished_sprt_game(
+        &self,
+        scheduled_game: &ScheduledGame<B>,
+        sprt_statuses: &[SprtStatus],
+    ) -> bool {
+        match self.tournament_type {
+            TournamentType::Sprt(_) => {
+                let candidate_id = scheduled_game
+                    .white_engine_id
+                    .0
+                    .max(scheduled_game.black_engine_id.0);
+                sprt_statuses[candidate_id - 1] != SprtStatus::Running
             }
+            _ => false,
         }
-        result
     }
 
-    fn next_unplayed_game(&self) -> Option<ScheduledGame<B>> {
+    /// Get the next game to play. For formats with dynamic scheduling, waits until the current round has finished,
+    /// and schedules the next round. Returns `None` when all games have been scheduled, or when shutting down
+    pub(crate) fn next_unplayed_game(
+        &self,
+        is_shutting_down: &AtomicBool,
+    ) -> Option<ScheduledGame<B>> {
         let mut games_schedule = self.games_schedule.lock().unwrap();
-        if let Some(scheduled_game) = games_schedule
-            .scheduled_games
-            .get(games_schedule.next_game_id)
-            .cloned()
+        loop {
+            // Skip games that were restored from an earlier run,
+            // and games of SPRT candidates that have already finished their test
+            let mut skipped_games = vec![];
+            {
+                let finished_games = self.finished_games.lock().unwrap();
+                let sprt_statuses = self.sprt_statuses.lock().unwrap();
+                loop {
+                    let game_id = games_schedule.next_game_id;
+                    if finished_games
+                        .get(game_id)
+                        .is_some_and(|game| game.is_some())
+                    {
+                        games_schedule.next_game_id += 1;
+                    } else if games_schedule
+                        .scheduled_games
+                        .get(game_id)
+                        .is_some_and(|game| self.is_finished_sprt_game(game, &sprt_statuses))
+                    {
+                        skipped_games.push(game_id);
+                        games_schedule.next_game_id += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            if !skipped_games.is_empty() {
+                self.pgn_writer.lock().unwrap().skip_games(skipped_games);
+            }
+            if let Some(scheduled_game) = games_schedule
+                .scheduled_games
+                .get(games_schedule.next_game_id)
+                .cloned()
+            {
+                games_schedule.next_game_id += 1;
+                return Some(scheduled_game);
+            }
+
+            let dynamic_schedule = self.dynamic_schedule.as_ref()?;
+            if is_shutting_down.load(atomic::Ordering::SeqCst) {
+                return None;
+            }
+            let mut finished_games = self.finished_games.lock().unwrap();
+            if finished_games.iter().all(Option::is_some) {
+                let next_round = dynamic_schedule
+                    .lock()
+                    .unwrap()
+                    .next_round(&games_schedule.scheduled_games, &finished_games);
+                if next_round.is_empty() {
+                    return None;
+                }
+                games_schedule.scheduled_games.extend(next_round);
+                finished_games.resize(games_schedule.scheduled_games.len(), None);
+            } else {
+                drop(finished_games);
+                // Wake up regularly to check for shutdown, in case a scheduled game is never played
+                games_schedule = self
+                    .game_finished
+                    .wait_timeout(games_schedule, Duration::from_millis(100))
+                    .unwrap()
+                    .0;
+            }
+        }
+    }
+}
+
+/// Match games from an earlier run to the scheduled games by their round tag.
+/// Fails if a game's opening doesn't match, which means that the earlier run was a different tournament
+pub(crate) fn restore_games<B: PgnPosition + Clone>(
+    scheduled_games: &[ScheduledGame<B>],
+    resumed_games: Vec<Game<B>>,
+) -> Result<Vec<Option<Game<B>>>, String> {
+    let mut finished_games = vec![None; scheduled_games.len()];
+
+    for game in resumed_games {
+        let Some(round_number) = game
+            .tags
+            .iter()
+            .find(|(tag, _)| tag == "Round")
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+            .and_then(|round| round.checked_sub(1))
+        else {
+            warn!("Ignoring resumed game without a valid round tag");
+            continue;
+        };
+        let Some(scheduled_game) = scheduled_games.get(round_number) else {
+            warn!(
+                "Ignoring resumed game #{}, the tournament only has {} games",
+                round_number + 1,
+                scheduled_games.len()
+            );
+            continue;
+        };
+        // If the opening doesn't match, the earlier run was a different tournament
+        if game.start_position.to_fen() != scheduled_game.opening.root_position.to_fen()
+            || game.moves.len() < scheduled_game.opening.moves.len()
+            || game
+                .moves
+                .iter()
+                .zip(scheduled_game.opening.moves.iter())
+                .any(|(ptn_move, opening_move)| ptn_move.mv != *opening_move)
         {
-            games_schedule.next_game_id += 1;
-            Some(scheduled_game)
-        } else {
-            None
+            return Err(format!(
+                "Game #{} in the ptn file does not match the scheduled opening. Was it started with different settings?",
+                round_number + 1
+            ));
+        }
+        if finished_games[round_number].is_some() {
+            warn!("Duplicate resumed game #{}, ignoring", round_number + 1);
+            continue;
         }
+        finished_games[round_number] = Some(game);
     }
+    Ok(finished_games)
 }
 
-fn print_head_to_head_score(
-    engine_wins: &[Vec<u64>],
-    engine_draws: &[Vec<u64>],
-    engine_names: &[String],
+/// Results between two engines, from the first engine's perspective
+struct PairResult {
     engine1_id: usize,
     engine2_id: usize,
-) {
-    let score = MatchScore {
-        wins: engine_wins[engine1_id][engine2_id],
-        draws: engine_draws[engine1_id][engine2_id],
-        losses: engine_wins[engine2_id][engine1_id],
-    };
+    score: MatchScore,
+    pentanomial: PentanomialResult,
+    estimate: ScoreEstimate,
+}
 
-    let full_simulation = simulation::FullWinstonSimulation::run_simulation(score);
+/// Give engines with the same name a numbered suffix, for example when the same binary is used with different options
+pub(crate) fn unique_names(names: Vec<String>) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(id, name)| {
+            if names.iter().filter(|other| *other == name).count() > 1 {
+                let number = names[..id].iter().filter(|other| *other == name).count() + 1;
+                format!("{} ({})", name, number)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+/// Pentanomial results of all game pairs between two engines, from the first engine's perspective.
+/// A game pair is two games with the same opening, with colors reversed.
+pub(crate) fn pair_penta_stats<B: PgnPosition>(
+    scheduled_games: &[ScheduledGame<B>],
+    finished_games: &[Option<Game<B>>],
+    engine1_id: usize,
+    engine2_id: usize,
+) -> PentanomialResult {
+    let mut result = PentanomialResult::default();
+    // Games waiting for the other game in their pair to finish, and engine 1's score in them
+    let mut unpaired_games: Vec<(&ScheduledGame<B>, u8)> = vec![];
 
-    let lower = full_simulation.result_for_p(0.025);
-    let expected = score.score();
-    let upper = full_simulation.result_for_p(0.975);
+    for (scheduled_game, game) in scheduled_games
+        .iter()
+        .zip(finished_games.iter())
+        .filter_map(|(a, b)| b.as_ref().map(|c| (a, c)))
+    {
+        let engine1_is_white = scheduled_game.white_engine_id.0 == engine1_id
+            && scheduled_game.black_engine_id.0 == engine2_id;
+        let engine1_is_black = scheduled_game.white_engine_id.0 == engine2_id
+            && scheduled_game.black_engine_id.0 == engine1_id;
+        if !engine1_is_white && !engine1_is_black {
+            continue;
+        }
+        let score = match (game.game_result().unwrap_or(Draw), engine1_is_white) {
+            (WhiteWin, true) | (BlackWin, false) => 2,
+            (Draw, _) => 1,
+            (WhiteWin, false) | (BlackWin, true) => 0,
+        };
+        if let Some(index) = unpaired_games.iter().position(|(other_game, _)| {
+            other_game.opening == scheduled_game.opening
+                && other_game.white_engine_id == scheduled_game.black_engine_id
+        }) {
+            let (_, other_score) = unpaired_games.swap_remove(index);
+            match (score, other_score) {
+                (0, 0) => result.ll += 1,
+                (0, 1) | (1, 0) => result.dl += 1,
+                (1, 1) => result.dd += 1,
+                (0, 2) | (2, 0) => result.wl += 1,
+                (1, 2) | (2, 1) => result.wd += 1,
+                _ => result.ww += 1,
+            }
+        } else {
+            unpaired_games.push((scheduled_game, score));
+        }
+    }
+    result
+}
 
-    let lower_elo = simulation::to_elo_string(lower);
-    let expected_elo = simulation::to_elo_string(expected);
-    let upper_elo = simulation::to_elo_string(upper);
+/// Print the score between two engines, with a confidence interval from the slower, but more accurate simulation
+fn print_head_to_head_score(pair_result: &PairResult, engine_names: &[String]) {
+    let estimate = ScoreEstimate::new(pair_result.score);
+    let lower_elo = simulation::to_elo_string(estimate.lower);
+    let expected_elo = simulation::to_elo_string(estimate.expected);
+    let upper_elo = simulation::to_elo_string(estimate.upper);
 
     println!(
         "{} vs {}: {}, {} elo [{}, {}] (95% confidence).",
-        engine_names[engine1_id],
-        engine_names[engine2_id],
-        score,
+        engine_names[pair_result.engine1_id],
+        engine_names[pair_result.engine2_id],
+        pair_result.score,
         expected_elo,
         lower_elo,
         upper_elo,
     );
 }
 
+/// One-line summary of the tournament's progress, printed between the full results
+fn status_line<B: PgnPosition>(
+    progress: &Progress,
+    games_played: usize,
+    num_games: usize,
+    finished_games: &[Option<Game<B>>],
+    pair_results: &[PairResult],
+    sprt_reports: &[SprtReport],
+) -> String {
+    let mut parts = vec![format!("{}/{} games", games_played, num_games)];
+    parts.push(format!("{:.1} games/min", progress.games_per_minute()));
+    if let Some(eta) = progress.eta(num_games.saturating_sub(games_played)) {
+        parts.push(format!("ETA {}", progress::format_duration(eta)));
+    }
+
+    if let [pair_result] = pair_results {
+        let estimate = pair_result.estimate;
+        parts.push(
+            match (
+                simulation::to_elo(estimate.lower),
+                simulation::to_elo(estimate.expected),
+                simulation::to_elo(estimate.upper),
+            ) {
+                (Some(lower), Some(expected), Some(upper)) => {
+                    format!("Elo {:+} ±{}", expected, (upper - lower) / 2)
+                }
+                _ => format!("Elo {}", simulation::to_elo_string(estimate.expected)),
+            },
+        );
+    }
+    if !sprt_reports.is_empty() {
+        let llrs: Vec<String> = sprt_reports
+            .iter()
+            .map(|sprt_report| format!("{:.2}", sprt_report.llr))
+            .collect();
+        parts.push(format!(
+            "LLR {} [{:.2}, {:.2}]",
+            llrs.join(", "),
+            sprt_reports[0].lower_bound,
+            sprt_reports[0].upper_bound
+        ));
+    }
+
+    let mut terminations: Vec<(&str, usize)> = vec![];
+    for game in finished_games.iter().flatten() {
+        let kind = stats::termination_kind(game);
+        match terminations.iter_mut().find(|(other, _)| *other == kind) {
+            Some((_, count)) => *count += 1,
+            None => terminations.push((kind, 1)),
+        }
+    }
+    if !terminations.is_empty() {
+        let counts: Vec<String> = terminations
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect();
+        parts.push(counts.join(", "));
+    }
+
+    parts.join(" | ")
+}
+
+/// Every round of a knockout tournament, with each match's score
+fn format_bracket<B: PgnPosition + Clone>(
+    knockout: &Knockout<B>,
+    scheduled_games: &[ScheduledGame<B>],
+    finished_games: &[Option<Game<B>>],
+    engine_names: &[String],
+) -> String {
+    use std::fmt::Write;
+    let mut output = String::new();
+    for (round_number, round) in knockout.bracket().iter().enumerate() {
+        writeln!(output, "Round {}", round_number + 1).unwrap();
+        for entry in round {
+            match entry {
+                BracketEntry::Bye(id) => {
+                    writeln!(output, "  {:16} bye", engine_names[*id]).unwrap()
+                }
+                BracketEntry::Match(knockout_match) => {
+                    let [score1, score2] = knockout_match.scores(scheduled_games, finished_games);
+                    let [id1, id2] = knockout_match.engine_ids;
+                    let status = match knockout_match.winner {
+                        Some(winner) if knockout_match.decided_by_elo => {
+                            format!("{} advances on elo", engine_names[winner])
+                        }
+                        Some(winner) => format!("{} advances", engine_names[winner]),
+                        None => String::new(),
+                    };
+                    writeln!(
+                        output,
+                        "  {:16} {:>4.1} - {:<4.1} {:16} {}",
+                        engine_names[id1],
+                        score1 as f32 / 2.0,
+                        score2 as f32 / 2.0,
+                        engine_names[id2],
+                        status
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+    if let Some(champion) = knockout.champion() {
+        writeln!(output, "Champion: {}", engine_names[champion]).unwrap();
+    }
+    output
+}
+
+/// Print a table of engines sorted by rating, with the likelihood of superiority over the next engine
+fn print_ratings(ratings: &Ratings, engine_names: &[String]) {
+    println!(
+        "{:>4} {:16} {:>6} {:>5} {:>7}",
+        "Rank", "Name", "Elo", "+/-", "LOS"
+    );
+    let ranking = ratings.ranking();
+    for (rank, id) in ranking.iter().enumerate() {
+        let los = match ranking.get(rank + 1) {
+            Some(next_id) => format!("{:6.1}%", 100.0 * ratings.los(*id, *next_id)),
+            None => String::new(),
+        };
+        println!(
+            "{:4} {:16} {:>+6.0} {:5.0} {:>7}",
+            rank + 1,
+            engine_names[*id],
+            ratings.elos[*id],
+            ratings.error_bar(*id),
+            los
+        );
+    }
+    println!(
+        "White advantage: {:+.0} elo, draw elo: {:.0}",
+        ratings.white_advantage, ratings.draw_elo
+    );
+}
+
+/// A worker thread's engines. Engines are only started once a game needs them
 pub(crate) struct Worker {
     pub id: usize,
-    pub engines: Vec<Engine>,
+    pub engines: Vec<Option<Engine>>,
+    builders: Vec<EngineBuilder>,
+    /// When each engine last played, as a count of the worker's games
+    last_used: Vec<usize>,
+    games_started: usize,
+}
+
+impl Worker {
+    fn new(id: usize, builders: Vec<EngineBuilder>) -> Self {
+        Worker {
+            id,
+            engines: builders.iter().map(|_| None).collect(),
+            last_used: vec![0; builders.len()],
+            builders,
+            games_started: 0,
+        }
+    }
+
+    /// The running engine with this id
+    pub fn engine(&mut self, id: usize) -> &mut Engine {
+        self.engines[id]
+            .as_mut()
+            .expect("Engine should have been started before the game")
+    }
 }
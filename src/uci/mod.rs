@@ -1,6 +1,8 @@
 use board_game_traits::Position;
 use std::error::Error;
 use std::fmt;
+use std::ops;
+use std::str::FromStr;
 
 pub mod parser;
 
@@ -111,13 +113,140 @@ impl UciOptionType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct UciInfo<B: Position> {
     pub depth: u16,
     pub seldepth: u16,
+    /// Search time in milliseconds
     pub time: i64,
     pub nodes: u64,
+    pub nps: u64,
+    /// How full the engine's hash table is, from 0.0 to 1.0
     pub hashfull: f64,
-    pub cp_score: i64,
-    pub pv: Vec<B::Move>, // One or more principal variations, sorted from best to worst
+    /// Which principal variation this is, if the engine sends several. Starts at 1
+    pub multipv: u16,
+    pub score: Score,
+    pub bound: ScoreBound,
+    /// The principal variation, starting with the move to be played
+    pub pv: Vec<B::Move>,
+}
+
+/// An engine's evaluation of a position, from the perspective of the side to move.
+/// Compare scores with `to_cp`, since mate scores are not ordered by their number of moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Score in centiflats
+    Cp(i64),
+    /// A forced win in this many moves, or a forced loss if negative
+    Mate(i64),
+}
+
+impl Score {
+    /// Centiflat value of mate scores, before subtracting the number of moves to mate
+    pub const MATE_CP: i64 = 100_000;
+
+    /// The score in centiflats, where mate scores are larger than any regular score.
+    /// Faster mates are scored higher
+    pub fn to_cp(self) -> i64 {
+        match self {
+            Score::Cp(cp) => cp,
+            Score::Mate(moves) if moves >= 0 => Score::MATE_CP - moves,
+            Score::Mate(moves) => -Score::MATE_CP - moves,
+        }
+    }
+
+    /// Parse a score from a PTN comment, with an optional bound suffix, like `+0.35+` for a lower bound
+    pub fn parse_with_bound(input: &str) -> Result<(Score, ScoreBound), UciError> {
+        let (score, bound) = match input.strip_suffix('+') {
+            Some(score) if !score.is_empty() => (score, ScoreBound::Lower),
+            _ => match input.strip_suffix('-') {
+                Some(score) if !score.is_empty() => (score, ScoreBound::Upper),
+                _ => (input, ScoreBound::Exact),
+            },
+        };
+        let invalid_score = || UciError::new_parse_error(format!("Invalid score \"{}\"", input));
+        let score = if let Some(moves) = score.strip_prefix("+M") {
+            moves
+                .parse()
+                .map(Score::Mate)
+                .map_err(|_| invalid_score())?
+        } else if let Some(moves) = score.strip_prefix("-M") {
+            moves
+                .parse::<i64>()
+                .map(|moves| Score::Mate(-moves))
+                .map_err(|_| invalid_score())?
+        } else {
+            score
+                .parse::<f64>()
+                .map(|score| Score::Cp((score * 100.0).round() as i64))
+                .map_err(|_| invalid_score())?
+        };
+        Ok((score, bound))
+    }
+}
+
+/// The score from the opponent's perspective
+impl ops::Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Score::Cp(cp) => Score::Cp(-cp),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    }
+}
+
+/// Format for PTN comments, like `+0.35` for centiflat scores and `+M3` for mate scores
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f64 / 100.0),
+            Score::Mate(moves) if *moves >= 0 => write!(f, "+M{}", moves),
+            Score::Mate(moves) => write!(f, "-M{}", -moves),
+        }
+    }
+}
+
+/// Parse a score written by `Display`. A bound suffix is accepted, but ignored
+impl FromStr for Score {
+    type Err = UciError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Score::parse_with_bound(input).map(|(score, _)| score)
+    }
+}
+
+/// Whether the score is exact, or only a bound on the real score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreBound {
+    Exact,
+    /// The real score is at least this high
+    Lower,
+    /// The real score is at most this high
+    Upper,
+}
+
+/// The bound from the opponent's perspective, where a lower bound becomes an upper bound
+impl ops::Neg for ScoreBound {
+    type Output = ScoreBound;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            ScoreBound::Exact => ScoreBound::Exact,
+            ScoreBound::Lower => ScoreBound::Upper,
+            ScoreBound::Upper => ScoreBound::Lower,
+        }
+    }
+}
+
+/// Suffix for scores in PTN comments, `+` for lower bounds and `-` for upper bounds
+impl fmt::Display for ScoreBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreBound::Exact => Ok(()),
+            ScoreBound::Lower => write!(f, "+"),
+            ScoreBound::Upper => write!(f, "-"),
+        }
+    }
 }
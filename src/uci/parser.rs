@@ -1,5 +1,6 @@
-use crate::uci::{UciError, UciErrorKind, UciInfo, UciOption, UciOptionType};
+use crate::uci::{Score, ScoreBound, UciError, UciErrorKind, UciInfo, UciOption, UciOptionType};
 use pgn_traits::PgnPosition;
+use std::error::Error;
 use std::result;
 use std::str::FromStr;
 use std::time::Duration;
@@ -166,10 +167,22 @@ pub fn parse_option(input: &str) -> result::Result<UciOption, UciError> {
     })
 }
 
-pub fn parse_info_string<B: PgnPosition>(input: &str) -> Result<UciInfo<B>, UciError> {
+/// Parse an `info` line sent by the engine while searching `position`.
+/// PV moves are replayed from `position`, and only the moves up to the first malformed or illegal move are kept
+pub fn parse_info_string<B: PgnPosition + Clone>(
+    input: &str,
+    position: &B,
+) -> Result<UciInfo<B>, UciError> {
     let mut pv: Vec<&str> = vec![];
-    let mut cp_score = None;
+    let mut score = None;
+    let mut bound = ScoreBound::Exact;
     let mut depth: u16 = 0;
+    let mut seldepth: u16 = 0;
+    let mut time: i64 = 0;
+    let mut nodes: u64 = 0;
+    let mut nps = None;
+    let mut hashfull: u16 = 0;
+    let mut multipv: u16 = 1;
 
     // These words are "special", and are used to delimit multi-word infos such as "pv"
     const KEYWORDS: &[&str] = &[
@@ -195,22 +208,23 @@ pub fn parse_info_string<B: PgnPosition>(input: &str) -> Result<UciInfo<B>, UciE
     assert_eq!(words_iter.next(), Some("info"));
     while let Some(next_token) = words_iter.next() {
         match next_token {
-            "score" => match words_iter.next() {
-                Some("cp") => {
-                    if let Some(cp_string) = words_iter.next() {
-                        cp_score = Some(i64::from_str(cp_string).map_err(|err| {
-                            UciError::new_caused_by(
-                                UciErrorKind::ParseError,
-                                format!("Failed to parse cp score \"{}\"", cp_string),
-                                Box::new(err),
-                            )
-                        })?);
-                    } else {
-                        return Err(UciError::new_parse_error("No cp score".to_string()));
-                    }
+            "score" => {
+                let (kind, value) = (words_iter.next(), words_iter.next());
+                let value: i64 = parse_info_value("score", value)?;
+                score = Some(match kind {
+                    Some("cp") => Score::Cp(value),
+                    Some("mate") => Score::Mate(value),
+                    _ => return Err(UciError::new_parse_error("Invalid score".to_string())),
+                });
+                bound = match words_iter.peek() {
+                    Some(&"lowerbound") => ScoreBound::Lower,
+                    Some(&"upperbound") => ScoreBound::Upper,
+                    _ => ScoreBound::Exact,
+                };
+                if bound != ScoreBound::Exact {
+                    words_iter.next();
                 }
-                _ => return Err(UciError::new_parse_error("Invalid score".to_string())),
-            },
+            }
             "pv" => {
                 if !pv.is_empty() {
                     return Err(UciError::new_parse_error(
@@ -224,36 +238,51 @@ pub fn parse_info_string<B: PgnPosition>(input: &str) -> Result<UciInfo<B>, UciE
                     pv.push(words_iter.next().unwrap());
                 }
             }
-            "depth" => {
-                if let Some(depth_string) = words_iter.next() {
-                    match depth_string.parse() {
-                        Ok(d) => depth = d,
-                        Err(err) => {
-                            return Err(UciError::new_caused_by(
-                                UciErrorKind::ParseError,
-                                format!("Failed to parse depth \"{}\"", depth_string),
-                                Box::new(err),
-                            ))
-                        }
-                    }
-                } else {
-                    return Err(UciError::new_parse_error(
-                        "Did not receive a value for key \"depth\"".to_string(),
-                    ));
-                }
-            }
+            "depth" => depth = parse_info_value(next_token, words_iter.next())?,
+            "seldepth" => seldepth = parse_info_value(next_token, words_iter.next())?,
+            "time" => time = parse_info_value(next_token, words_iter.next())?,
+            "nodes" => nodes = parse_info_value(next_token, words_iter.next())?,
+            "nps" => nps = Some(parse_info_value(next_token, words_iter.next())?),
+            "hashfull" => hashfull = parse_info_value(next_token, words_iter.next())?,
+            "multipv" => multipv = parse_info_value(next_token, words_iter.next())?,
+            // The rest of the line is free-form text
+            "string" => break,
             _ => (),
         }
     }
-    if let Some(score) = cp_score {
+
+    let mut pv_position = position.clone();
+    let mut pv_moves = vec![];
+    for move_string in pv {
+        let Ok(mv) = pv_position.move_from_lan(move_string) else {
+            break;
+        };
+        let mut legal_moves = vec![];
+        pv_position.generate_moves(&mut legal_moves);
+        if pv_position.game_result().is_some() || !legal_moves.contains(&mv) {
+            break;
+        }
+        pv_position.do_move(mv.clone());
+        pv_moves.push(mv);
+    }
+
+    if let Some(score) = score {
         Ok(UciInfo {
             depth,
-            seldepth: 0,
-            time: 0,
-            nodes: 0,
-            hashfull: 0.0,
-            cp_score: score,
-            pv: vec![],
+            seldepth,
+            time,
+            nodes,
+            // Calculate nps ourselves if the engine does not send it
+            nps: nps.unwrap_or(if time > 0 {
+                nodes.saturating_mul(1000) / time as u64
+            } else {
+                0
+            }),
+            hashfull: hashfull as f64 / 1000.0,
+            multipv,
+            score,
+            bound,
+            pv: pv_moves,
         })
     } else {
         Err(UciError::new_root(
@@ -263,6 +292,36 @@ pub fn parse_info_string<B: PgnPosition>(input: &str) -> Result<UciInfo<B>, UciE
     }
 }
 
+fn parse_info_value<T: FromStr>(key: &str, value: Option<&str>) -> Result<T, UciError>
+where
+    T::Err: Error + 'static,
+{
+    let Some(value) = value else {
+        return Err(UciError::new_parse_error(format!(
+            "Did not receive a value for key \"{}\"",
+            key
+        )));
+    };
+    value.parse().map_err(|err| {
+        UciError::new_caused_by(
+            UciErrorKind::ParseError,
+            format!("Failed to parse {} \"{}\"", key, value),
+            Box::new(err),
+        )
+    })
+}
+
+pub fn parse_movetime(input: &str) -> Result<Duration, UciError> {
+    let error = || UciError::new_parse_error(format!("Couldn't parse movetime \"{}\"", input));
+    let seconds = f64::from_str(input).map_err(|_| error())?;
+    // Movetime is sent to the engine in whole milliseconds, so values below 1ms are rejected instead of becoming 0
+    let milliseconds = (seconds * 1000.0).round();
+    if !milliseconds.is_finite() || milliseconds < 1.0 {
+        return Err(error());
+    }
+    Ok(Duration::from_millis(milliseconds as u64))
+}
+
 pub fn parse_tc(input: &str) -> Result<(Duration, Duration), UciError> {
     let error = || UciError::new_parse_error(format!("Couldn't parse tc \"{}\"", input));
     let mut parts = input.split('+');